@@ -0,0 +1,145 @@
+/* Decompression subset of the libzstd API, generated by cbindgen. */
+
+#ifndef ZSTD_DECOMPRESSOR_H
+#define ZSTD_DECOMPRESSOR_H
+
+#include <stddef.h>
+
+#define ZSTD_MAGICNUMBER 4247762216
+
+#define ZSTD_MAGIC_SKIPPABLE_START 407710288
+
+#define ZSTD_MAGIC_SKIPPABLE_MASK 4294967280
+
+/**
+ * Returned by `ZSTD_getFrameContentSize` when the frame header has no content size
+ */
+#define ZSTD_CONTENTSIZE_UNKNOWN 18446744073709551615ull
+
+/**
+ * Returned by `ZSTD_getFrameContentSize` when the input does not start with a valid
+ * frame header
+ */
+#define ZSTD_CONTENTSIZE_ERROR 18446744073709551614ull
+
+/**
+ * Streaming decompression state, allocated by `ZSTD_createDStream`
+ */
+typedef struct ZSTD_DStream ZSTD_DStream;
+
+/**
+ * Output of `ZSTD_decompressStream`, filled from `pos` to `size`
+ */
+typedef struct ZSTD_outBuffer {
+  void *dst;
+  size_t size;
+  size_t pos;
+} ZSTD_outBuffer;
+
+/**
+ * Input of `ZSTD_decompressStream`, consumed from `pos` to `size`
+ */
+typedef struct ZSTD_inBuffer {
+  const void *src;
+  size_t size;
+  size_t pos;
+} ZSTD_inBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Decompress all the frames of `src` into `dst`. Return the number of bytes written,
+ * or an error code if the content does not fit in `dst` or the input is invalid.
+ *
+ * # Safety
+ *
+ * `src` must be readable for `srcSize` bytes and `dst` writable for `dstCapacity`
+ * bytes, they may be null when their size is 0.
+ */
+size_t ZSTD_decompress(void *dst, size_t dstCapacity, const void *src, size_t srcSize);
+
+/**
+ * Decompressed size of the frame starting `src`, 0 for a skippable frame,
+ * `ZSTD_CONTENTSIZE_UNKNOWN` if the header does not give it, or `ZSTD_CONTENTSIZE_ERROR`
+ * if `src` does not start with a valid frame header.
+ *
+ * # Safety
+ *
+ * `src` must be readable for `srcSize` bytes, it may be null when `srcSize` is 0.
+ */
+unsigned long long ZSTD_getFrameContentSize(const void *src, size_t srcSize);
+
+/**
+ * Compressed size of the frame starting `src`, skippable frames included, or an error
+ * code if the frame is invalid or incomplete.
+ *
+ * # Safety
+ *
+ * `src` must be readable for `srcSize` bytes, it may be null when `srcSize` is 0.
+ */
+size_t ZSTD_findFrameCompressedSize(const void *src, size_t srcSize);
+
+/**
+ * Tell whether a returned `size_t` is an error code
+ */
+unsigned int ZSTD_isError(size_t code);
+
+/**
+ * Description of a returned `size_t`, "No error detected" if it is not an error code.
+ * The string is static.
+ */
+const char *ZSTD_getErrorName(size_t code);
+
+struct ZSTD_DStream *ZSTD_createDStream(void);
+
+/**
+ * Free a stream created with `ZSTD_createDStream`, does nothing if `zds` is null
+ *
+ * # Safety
+ *
+ * `zds` must be null or come from `ZSTD_createDStream` and not be freed yet.
+ */
+size_t ZSTD_freeDStream(struct ZSTD_DStream *zds);
+
+/**
+ * Start decoding a new input, e.g. after an error. Return the size of the input needed
+ * to start a frame.
+ *
+ * # Safety
+ *
+ * `zds` must come from `ZSTD_createDStream` and not be freed yet.
+ */
+size_t ZSTD_initDStream(struct ZSTD_DStream *zds);
+
+/**
+ * Decode the input from `input->pos` and write the content from `output->pos`, moving
+ * both positions forward. Return 0 when a frame is finished and fully flushed, a
+ * non-zero hint when more input is expected or the output is full, or an error code.
+ * The stream must be reset with `ZSTD_initDStream` after an error.
+ *
+ * # Safety
+ *
+ * `zds` must come from `ZSTD_createDStream` and not be freed yet. `output` and `input`
+ * must be valid, and their buffers valid for their `size`.
+ */
+size_t ZSTD_decompressStream(struct ZSTD_DStream *zds,
+                             struct ZSTD_outBuffer *output,
+                             struct ZSTD_inBuffer *input);
+
+/**
+ * Recommended size of the input buffers, a whole block with its header
+ */
+size_t ZSTD_DStreamInSize(void);
+
+/**
+ * Recommended size of the output buffers, a whole block
+ */
+size_t ZSTD_DStreamOutSize(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ZSTD_DECOMPRESSOR_H */
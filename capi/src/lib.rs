@@ -0,0 +1,464 @@
+//! C API of the decoder, exporting the decompression functions of libzstd: single pass
+//! decompression, frame sizes, error codes and streaming decompression. The header is
+//! `include/zstd.h`.
+#![allow(non_camel_case_types, non_snake_case)]
+
+use std::{
+    ffi::{c_char, c_uint, c_ulonglong, c_void, CStr},
+    slice,
+};
+
+use zstd_decompressor::{
+    block::{Block, MAX_BLOCK_SIZE},
+    decompressor::Decompressor,
+    frame::{self, Header},
+    options::DecoderOptions,
+    parsing::ForwardByteParser,
+    stream::StreamDecoder,
+    Error, ErrorKind,
+};
+
+pub const ZSTD_MAGICNUMBER: c_uint = 0xFD2FB528;
+pub const ZSTD_MAGIC_SKIPPABLE_START: c_uint = 0x184D2A50;
+pub const ZSTD_MAGIC_SKIPPABLE_MASK: c_uint = 0xFFFFFFF0;
+
+/// Returned by `ZSTD_getFrameContentSize` when the frame header has no content size
+pub const ZSTD_CONTENTSIZE_UNKNOWN: c_ulonglong = 0xFFFF_FFFF_FFFF_FFFF;
+/// Returned by `ZSTD_getFrameContentSize` when the input does not start with a valid
+/// frame header
+pub const ZSTD_CONTENTSIZE_ERROR: c_ulonglong = 0xFFFF_FFFF_FFFF_FFFE;
+
+/// Largest window accepted by `ZSTD_decompress`, the largest one libzstd decodes
+const MAX_WINDOW_SIZE: u64 = 1 << 31;
+/// Largest window accepted by the streaming decoder, the default of libzstd
+const MAX_STREAM_WINDOW_SIZE: u64 = 1 << 27;
+/// Magic number and frame header descriptor, the input needed to start a frame
+const FRAME_HEADER_PREFIX_SIZE: usize = 5;
+
+/// Error codes of libzstd, returned as `(size_t)-code`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorCode {
+    Generic = 1,
+    PrefixUnknown = 10,
+    WindowTooLarge = 16,
+    CorruptionDetected = 20,
+    ChecksumWrong = 22,
+    DictionaryWrong = 32,
+    DstSizeTooSmall = 70,
+    SrcSizeWrong = 72,
+    /// Codes above this one are not errors
+    MaxCode = 120,
+}
+
+impl ErrorCode {
+    const ALL: [ErrorCode; 8] = [
+        ErrorCode::Generic,
+        ErrorCode::PrefixUnknown,
+        ErrorCode::WindowTooLarge,
+        ErrorCode::CorruptionDetected,
+        ErrorCode::ChecksumWrong,
+        ErrorCode::DictionaryWrong,
+        ErrorCode::DstSizeTooSmall,
+        ErrorCode::SrcSizeWrong,
+    ];
+
+    fn from_kind(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::UnexpectedEnd => ErrorCode::SrcSizeWrong,
+            ErrorKind::UnrecognizedMagic => ErrorCode::PrefixUnknown,
+            ErrorKind::ReservedValue
+            | ErrorKind::Corrupted
+            | ErrorKind::BlockTooBig
+            | ErrorKind::ContentSizeMismatch => ErrorCode::CorruptionDetected,
+            ErrorKind::WindowTooBig => ErrorCode::WindowTooLarge,
+            ErrorKind::ChecksumMismatch => ErrorCode::ChecksumWrong,
+            ErrorKind::MissingDictionary | ErrorKind::DictionaryMismatch => {
+                ErrorCode::DictionaryWrong
+            }
+            ErrorKind::OutputTooBig | ErrorKind::DstTooSmall => ErrorCode::DstSizeTooSmall,
+            ErrorKind::TooManyFrames
+            | ErrorKind::TooManyBlocks
+            | ErrorKind::Io
+            | ErrorKind::Thread => ErrorCode::Generic,
+        }
+    }
+
+    /// Value returned to the caller
+    fn code(self) -> usize {
+        (self as usize).wrapping_neg()
+    }
+
+    fn name(self) -> &'static CStr {
+        match self {
+            ErrorCode::Generic => c"Error (generic)",
+            ErrorCode::PrefixUnknown => c"Unknown frame descriptor",
+            ErrorCode::WindowTooLarge => c"Frame requires too much memory for decoding",
+            ErrorCode::CorruptionDetected => c"Data corruption detected",
+            ErrorCode::ChecksumWrong => c"Restored data doesn't match checksum",
+            ErrorCode::DictionaryWrong => c"Dictionary mismatch",
+            ErrorCode::DstSizeTooSmall => c"Destination buffer is too small",
+            ErrorCode::SrcSizeWrong => c"Src size is incorrect",
+            ErrorCode::MaxCode => c"Unspecified error code",
+        }
+    }
+}
+
+fn error_code(error: &Error) -> usize {
+    ErrorCode::from_kind(error.kind()).code()
+}
+
+/// View `size` bytes at `ptr`, which may be null when `size` is 0
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `size` bytes for `'a`.
+unsafe fn input<'a>(ptr: *const c_void, size: usize) -> &'a [u8] {
+    if size == 0 {
+        &[]
+    } else {
+        // SAFETY: guaranteed by the caller
+        unsafe { slice::from_raw_parts(ptr.cast(), size) }
+    }
+}
+
+/// View `size` bytes at `ptr` as an output buffer, `ptr` may be null when `size` is 0
+///
+/// # Safety
+///
+/// `ptr` must be valid for writes of `size` bytes for `'a`, and not aliased.
+unsafe fn output<'a>(ptr: *mut c_void, size: usize) -> &'a mut [u8] {
+    if size == 0 {
+        &mut []
+    } else {
+        // SAFETY: guaranteed by the caller
+        unsafe { slice::from_raw_parts_mut(ptr.cast(), size) }
+    }
+}
+
+/// Decompress all the frames of `src` into `dst`. Return the number of bytes written,
+/// or an error code if the content does not fit in `dst` or the input is invalid.
+///
+/// # Safety
+///
+/// `src` must be readable for `srcSize` bytes and `dst` writable for `dstCapacity`
+/// bytes, they may be null when their size is 0.
+#[no_mangle]
+pub unsafe extern "C" fn ZSTD_decompress(
+    dst: *mut c_void,
+    dstCapacity: usize,
+    src: *const c_void,
+    srcSize: usize,
+) -> usize {
+    // SAFETY: guaranteed by the caller
+    let (dst, src) = unsafe { (output(dst, dstCapacity), input(src, srcSize)) };
+
+    let mut decompressor =
+        Decompressor::new().with_options(DecoderOptions::new().max_window_size(MAX_WINDOW_SIZE));
+    decompressor
+        .decompress_into(src, dst)
+        .unwrap_or_else(|e| error_code(&e))
+}
+
+/// Decompressed size of the frame starting `src`, 0 for a skippable frame,
+/// `ZSTD_CONTENTSIZE_UNKNOWN` if the header does not give it, or `ZSTD_CONTENTSIZE_ERROR`
+/// if `src` does not start with a valid frame header.
+///
+/// # Safety
+///
+/// `src` must be readable for `srcSize` bytes, it may be null when `srcSize` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn ZSTD_getFrameContentSize(
+    src: *const c_void,
+    srcSize: usize,
+) -> c_ulonglong {
+    // SAFETY: guaranteed by the caller
+    let mut parser = ForwardByteParser::new(unsafe { input(src, srcSize) });
+
+    match parser.le_u32() {
+        Ok(ZSTD_MAGICNUMBER) => match Header::parse(&mut parser) {
+            Ok(header) => header.content_size.unwrap_or(ZSTD_CONTENTSIZE_UNKNOWN),
+            Err(_) => ZSTD_CONTENTSIZE_ERROR,
+        },
+        Ok(magic) if magic & ZSTD_MAGIC_SKIPPABLE_MASK == ZSTD_MAGIC_SKIPPABLE_START => 0,
+        _ => ZSTD_CONTENTSIZE_ERROR,
+    }
+}
+
+/// Compressed size of the frame starting `src`, skippable frames included, or an error
+/// code if the frame is invalid or incomplete.
+///
+/// # Safety
+///
+/// `src` must be readable for `srcSize` bytes, it may be null when `srcSize` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn ZSTD_findFrameCompressedSize(src: *const c_void, srcSize: usize) -> usize {
+    // SAFETY: guaranteed by the caller
+    let src = unsafe { input(src, srcSize) };
+    let mut parser = ForwardByteParser::new(src);
+
+    match skip_frame(&mut parser) {
+        Ok(()) => src.len() - parser.len(),
+        Err(e) => error_code(&e),
+    }
+}
+
+/// Skip the frame starting `parser` like libzstd, only parsing the frame and block headers
+fn skip_frame(parser: &mut ForwardByteParser) -> Result<(), Error> {
+    match parser.le_u32()? {
+        ZSTD_MAGICNUMBER => {
+            let header = Header::parse(parser)?;
+            loop {
+                let (last, block_type, block_size) = Block::parse_header(parser)?;
+                parser.slice(Block::content_size(
+                    block_type,
+                    block_size,
+                    header.block_maximum_size(),
+                )?)?;
+                if last {
+                    break;
+                }
+            }
+            if header.content_checksum_flag {
+                parser.slice(4)?;
+            }
+        }
+        magic if magic & ZSTD_MAGIC_SKIPPABLE_MASK == ZSTD_MAGIC_SKIPPABLE_START => {
+            let size = parser.le_u32()? as usize;
+            parser.slice(size)?;
+        }
+        magic => return Err(frame::Error::UnrecognizedMagic(magic).into()),
+    }
+
+    Ok(())
+}
+
+/// Tell whether a returned `size_t` is an error code
+#[no_mangle]
+pub extern "C" fn ZSTD_isError(code: usize) -> c_uint {
+    (code > ErrorCode::MaxCode.code()) as c_uint
+}
+
+/// Description of a returned `size_t`, "No error detected" if it is not an error code.
+/// The string is static.
+#[no_mangle]
+pub extern "C" fn ZSTD_getErrorName(code: usize) -> *const c_char {
+    if ZSTD_isError(code) == 0 {
+        return c"No error detected".as_ptr();
+    }
+
+    ErrorCode::ALL
+        .into_iter()
+        .find(|error| error.code() == code)
+        .unwrap_or(ErrorCode::MaxCode)
+        .name()
+        .as_ptr()
+}
+
+/// Input of `ZSTD_decompressStream`, consumed from `pos` to `size`
+#[repr(C)]
+pub struct ZSTD_inBuffer {
+    pub src: *const c_void,
+    pub size: usize,
+    pub pos: usize,
+}
+
+/// Output of `ZSTD_decompressStream`, filled from `pos` to `size`
+#[repr(C)]
+pub struct ZSTD_outBuffer {
+    pub dst: *mut c_void,
+    pub size: usize,
+    pub pos: usize,
+}
+
+/// Streaming decompression state, allocated by `ZSTD_createDStream`
+pub struct ZSTD_DStream {
+    decoder: StreamDecoder,
+    /// Decoded content not copied to an output buffer yet
+    decoded: Vec<u8>,
+    /// Position of the next byte to copy in `decoded`
+    pos: usize,
+    /// The input fed so far ends inside a frame
+    needs_more_input: bool,
+}
+
+impl ZSTD_DStream {
+    fn new() -> Self {
+        ZSTD_DStream {
+            decoder: StreamDecoder::new()
+                .with_options(DecoderOptions::new().max_window_size(MAX_STREAM_WINDOW_SIZE)),
+            decoded: Vec::new(),
+            pos: 0,
+            needs_more_input: true,
+        }
+    }
+
+    /// Copy as much decoded content as possible to `dst`, return whether all of it was
+    fn flush(&mut self, dst: &mut [u8], pos: &mut usize) -> bool {
+        let available = &self.decoded[self.pos..];
+        let len = available.len().min(dst.len() - *pos);
+        dst[*pos..*pos + len].copy_from_slice(&available[..len]);
+        *pos += len;
+        self.pos += len;
+
+        if self.pos < self.decoded.len() {
+            return false;
+        }
+        self.decoded.clear();
+        self.pos = 0;
+        true
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn ZSTD_createDStream() -> *mut ZSTD_DStream {
+    Box::into_raw(Box::new(ZSTD_DStream::new()))
+}
+
+/// Free a stream created with `ZSTD_createDStream`, does nothing if `zds` is null
+///
+/// # Safety
+///
+/// `zds` must be null or come from `ZSTD_createDStream` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ZSTD_freeDStream(zds: *mut ZSTD_DStream) -> usize {
+    if !zds.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { Box::from_raw(zds) });
+    }
+    0
+}
+
+/// Start decoding a new input, e.g. after an error. Return the size of the input needed
+/// to start a frame.
+///
+/// # Safety
+///
+/// `zds` must come from `ZSTD_createDStream` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ZSTD_initDStream(zds: *mut ZSTD_DStream) -> usize {
+    // SAFETY: guaranteed by the caller
+    unsafe { *zds = ZSTD_DStream::new() };
+    FRAME_HEADER_PREFIX_SIZE
+}
+
+/// Decode the input from `input->pos` and write the content from `output->pos`, moving
+/// both positions forward. Return 0 when a frame is finished and fully flushed, a
+/// non-zero hint when more input is expected or the output is full, or an error code.
+/// The stream must be reset with `ZSTD_initDStream` after an error.
+///
+/// # Safety
+///
+/// `zds` must come from `ZSTD_createDStream` and not be freed yet. `output` and `input`
+/// must be valid, and their buffers valid for their `size`.
+#[no_mangle]
+pub unsafe extern "C" fn ZSTD_decompressStream(
+    zds: *mut ZSTD_DStream,
+    output: *mut ZSTD_outBuffer,
+    input: *mut ZSTD_inBuffer,
+) -> usize {
+    // SAFETY: guaranteed by the caller
+    let (zds, output, input) = unsafe { (&mut *zds, &mut *output, &mut *input) };
+    if input.pos > input.size {
+        return ErrorCode::SrcSizeWrong.code();
+    }
+    if output.pos > output.size {
+        return ErrorCode::DstSizeTooSmall.code();
+    }
+    // SAFETY: guaranteed by the caller
+    let (dst, src) = unsafe {
+        (
+            self::output(output.dst, output.size),
+            self::input(input.src, input.size),
+        )
+    };
+
+    // Stop decoding once the output is full, so that at most one block more than the
+    // output buffer, or than `ZSTD_DStreamOutSize`, is kept
+    while zds.flush(dst, &mut output.pos) && input.pos < input.size {
+        let available = (output.size - output.pos).clamp(1, ZSTD_DStreamOutSize());
+        let result =
+            zds.decoder
+                .feed_bounded(&src[input.pos..], &mut zds.decoded, available as u64);
+        match result {
+            Ok(progress) => {
+                zds.needs_more_input = progress.needs_more_input;
+                input.pos += progress.consumed;
+            }
+            Err(e) => return error_code(&e),
+        }
+    }
+
+    if zds.pos < zds.decoded.len() {
+        zds.decoded.len() - zds.pos
+    } else if zds.needs_more_input {
+        FRAME_HEADER_PREFIX_SIZE
+    } else {
+        0
+    }
+}
+
+/// Recommended size of the input buffers, a whole block with its header
+#[no_mangle]
+pub extern "C" fn ZSTD_DStreamInSize() -> usize {
+    MAX_BLOCK_SIZE + 3
+}
+
+/// Recommended size of the output buffers, a whole block
+#[no_mangle]
+pub extern "C" fn ZSTD_DStreamOutSize() -> usize {
+    MAX_BLOCK_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frame of `blocks` RLE blocks of 128KiB, 4 bytes each
+    fn rle_frame(blocks: usize) -> Vec<u8> {
+        // Magic number, frame header descriptor and a 128KiB window
+        let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd, 0x00, 0x38];
+        for index in 0..blocks {
+            let last = (index + 1 == blocks) as u32;
+            let header = (MAX_BLOCK_SIZE as u32) << 3 | 1 << 1 | last;
+            frame.extend_from_slice(&header.to_le_bytes()[..3]);
+            frame.push(b'a');
+        }
+        frame
+    }
+
+    #[test]
+    fn decompress_stream_output_bounded() {
+        let frame = rle_frame(64);
+        let mut buffer = [0; 1000];
+        let mut input = ZSTD_inBuffer {
+            src: frame.as_ptr().cast(),
+            size: frame.len(),
+            pos: 0,
+        };
+        let mut total = 0;
+
+        let zds = ZSTD_createDStream();
+        loop {
+            let mut output = ZSTD_outBuffer {
+                dst: buffer.as_mut_ptr().cast(),
+                size: buffer.len(),
+                pos: 0,
+            };
+            // SAFETY: the stream and the buffers are valid
+            let result = unsafe { ZSTD_decompressStream(zds, &mut output, &mut input) };
+            assert_eq!(0, ZSTD_isError(result));
+            // SAFETY: the stream is valid and not used by the call anymore
+            assert!(unsafe { &*zds }.decoded.len() <= MAX_BLOCK_SIZE);
+            assert!(buffer[..output.pos].iter().all(|&byte| byte == b'a'));
+            total += output.pos;
+            if result == 0 {
+                break;
+            }
+        }
+        // SAFETY: created above
+        unsafe { ZSTD_freeDStream(zds) };
+
+        assert_eq!(frame.len(), input.pos);
+        assert_eq!(64 * MAX_BLOCK_SIZE, total);
+    }
+}
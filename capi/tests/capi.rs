@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod capi_tests {
+    use std::{env, fs, path::PathBuf, process::Command};
+
+    use zstd_decompressor::{decompressor::Decompressor, options::DecoderOptions};
+
+    const MANIFEST_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+    /// Directory of the libzstd.so built with the tests, `deps` like the test binary
+    fn library_dir() -> PathBuf {
+        let exe = env::current_exe().unwrap();
+        exe.parent().unwrap().to_path_buf()
+    }
+
+    #[test]
+    fn header_is_up_to_date() {
+        let mut generated = Vec::new();
+        cbindgen::generate(MANIFEST_DIR)
+            .unwrap()
+            .write(&mut generated);
+
+        let header = fs::read(format!("{MANIFEST_DIR}/include/zstd.h")).unwrap();
+        assert!(
+            header == generated,
+            "include/zstd.h is outdated, regenerate it with cbindgen"
+        );
+    }
+
+    #[test]
+    fn c_program() {
+        let tmp = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("capi");
+        fs::create_dir_all(&tmp).unwrap();
+
+        let program = tmp.join("decompress");
+        let lib = library_dir();
+        let status = Command::new("cc")
+            .arg(format!("{MANIFEST_DIR}/tests/decompress.c"))
+            .arg(format!("-I{MANIFEST_DIR}/include"))
+            .arg(format!("-L{}", lib.display()))
+            .arg(format!("-Wl,-rpath,{}", lib.display()))
+            .arg("-lzstd")
+            .arg("-o")
+            .arg(&program)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // The expected content of each file is decoded with the Rust API
+        let mut args = Vec::new();
+        for name in [
+            "romeo.txt.zst",
+            "romeo3.txt.zst",
+            "romeo-wlog10.txt.zst",
+            "romeo-19-wlog10.txt.zst",
+            "romeo-long27.txt.zst",
+            "skippables.zst",
+            "welcome.zst",
+            "huffman-pow2.txt.zst",
+        ] {
+            let path = format!("{MANIFEST_DIR}/../resources/{name}");
+            let expected = Decompressor::new()
+                .with_options(DecoderOptions::new().max_window_size(1 << 27))
+                .decompress(&fs::read(&path).unwrap())
+                .unwrap();
+            let expected_path = tmp.join(name.replace(".zst", ".out"));
+            fs::write(&expected_path, expected).unwrap();
+            args.push(PathBuf::from(path));
+            args.push(expected_path);
+        }
+
+        let status = Command::new(&program).args(&args).status().unwrap();
+        assert!(status.success());
+    }
+}
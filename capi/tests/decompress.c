@@ -0,0 +1,159 @@
+/* Decompress pairs of compressed and expected files with every function of zstd.h:
+ *
+ *     decompress <file.zst> <expected> [<file.zst> <expected>]...
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "zstd.h"
+
+#define CHECK(condition)                                                        \
+    do {                                                                        \
+        if (!(condition)) {                                                     \
+            fprintf(stderr, "%s:%d: %s: check failed: %s\n", __FILE__,          \
+                    __LINE__, path, #condition);                                \
+            return 1;                                                           \
+        }                                                                       \
+    } while (0)
+
+/* Read the whole file at `path`, with one more byte so empty files are allocated */
+static unsigned char *read_file(const char *path, size_t *size) {
+    FILE *file = fopen(path, "rb");
+    if (file == NULL) {
+        return NULL;
+    }
+    fseek(file, 0, SEEK_END);
+    *size = (size_t)ftell(file);
+    fseek(file, 0, SEEK_SET);
+
+    unsigned char *data = malloc(*size + 1);
+    if (data != NULL && fread(data, 1, *size, file) != *size) {
+        free(data);
+        data = NULL;
+    }
+    fclose(file);
+    return data;
+}
+
+/* Walk the frames with ZSTD_findFrameCompressedSize and ZSTD_getFrameContentSize */
+static int check_frames(const char *path, const unsigned char *src, size_t src_size,
+                        size_t expected_size) {
+    unsigned long long content_size = 0;
+    int known = 1;
+    size_t pos = 0;
+
+    while (pos < src_size) {
+        size_t frame_size = ZSTD_findFrameCompressedSize(src + pos, src_size - pos);
+        CHECK(!ZSTD_isError(frame_size));
+        CHECK(frame_size > 0 && frame_size <= src_size - pos);
+
+        unsigned long long size = ZSTD_getFrameContentSize(src + pos, src_size - pos);
+        CHECK(size != ZSTD_CONTENTSIZE_ERROR);
+        if (size == ZSTD_CONTENTSIZE_UNKNOWN) {
+            known = 0;
+        } else {
+            content_size += size;
+        }
+        pos += frame_size;
+    }
+    /* Skippable frames count as empty, their content is only output by the decoder */
+    CHECK(!known || content_size <= expected_size);
+
+    return 0;
+}
+
+static int check_decompress(const char *path, const unsigned char *src, size_t src_size,
+                            const unsigned char *expected, size_t expected_size) {
+    unsigned char *dst = malloc(expected_size + 1);
+    CHECK(dst != NULL);
+
+    size_t written = ZSTD_decompress(dst, expected_size, src, src_size);
+    CHECK(!ZSTD_isError(written));
+    CHECK(written == expected_size);
+    CHECK(memcmp(dst, expected, expected_size) == 0);
+
+    if (expected_size > 0) {
+        size_t error = ZSTD_decompress(dst, expected_size - 1, src, src_size);
+        CHECK(ZSTD_isError(error));
+        CHECK(strcmp(ZSTD_getErrorName(error), "Destination buffer is too small") == 0);
+    }
+
+    size_t error = ZSTD_decompress(dst, expected_size, src, src_size - 1);
+    CHECK(ZSTD_isError(error));
+
+    free(dst);
+    return 0;
+}
+
+/* Decompress with small input and output buffers, so blocks and frames are split */
+static int check_stream(const char *path, const unsigned char *src, size_t src_size,
+                        const unsigned char *expected, size_t expected_size) {
+    unsigned char *dst = malloc(expected_size + 1);
+    unsigned char buffer[101];
+    size_t written = 0;
+    size_t result = 1;
+    CHECK(dst != NULL);
+
+    ZSTD_DStream *zds = ZSTD_createDStream();
+    CHECK(zds != NULL);
+    CHECK(!ZSTD_isError(ZSTD_initDStream(zds)));
+
+    ZSTD_inBuffer input = {src, 0, 0};
+    while (input.pos < src_size || result != 0) {
+        input.size = input.pos + 13 < src_size ? input.pos + 13 : src_size;
+        ZSTD_outBuffer output = {buffer, sizeof(buffer), 0};
+
+        result = ZSTD_decompressStream(zds, &output, &input);
+        CHECK(!ZSTD_isError(result));
+        CHECK(written + output.pos <= expected_size);
+        memcpy(dst + written, buffer, output.pos);
+        written += output.pos;
+        /* No progress is possible at the end of a truncated input */
+        CHECK(input.pos < src_size || output.pos > 0 || result == 0);
+    }
+    CHECK(written == expected_size);
+    CHECK(memcmp(dst, expected, expected_size) == 0);
+
+    /* An invalid magic number is an error, and the stream can be reused after it */
+    ZSTD_inBuffer garbage = {"garbage", 7, 0};
+    ZSTD_outBuffer output = {buffer, sizeof(buffer), 0};
+    CHECK(ZSTD_isError(ZSTD_decompressStream(zds, &output, &garbage)));
+    CHECK(ZSTD_initDStream(zds) > 0);
+    input.pos = 0;
+    input.size = src_size < ZSTD_DStreamInSize() ? src_size : ZSTD_DStreamInSize();
+    CHECK(!ZSTD_isError(ZSTD_decompressStream(zds, &output, &input)));
+
+    ZSTD_freeDStream(zds);
+    free(dst);
+    return 0;
+}
+
+int main(int argc, char **argv) {
+    const char *path = argv[0];
+    CHECK(argc % 2 == 1);
+    CHECK(!ZSTD_isError(0));
+    CHECK(strcmp(ZSTD_getErrorName(0), "No error detected") == 0);
+    CHECK(ZSTD_getFrameContentSize("garbage", 7) == ZSTD_CONTENTSIZE_ERROR);
+    CHECK(ZSTD_isError(ZSTD_findFrameCompressedSize("garbage", 7)));
+    CHECK(ZSTD_DStreamOutSize() > 0);
+    CHECK(ZSTD_freeDStream(NULL) == 0);
+
+    for (int i = 1; i < argc; i += 2) {
+        size_t src_size, expected_size;
+        path = argv[i];
+        unsigned char *src = read_file(argv[i], &src_size);
+        unsigned char *expected = read_file(argv[i + 1], &expected_size);
+        CHECK(src != NULL && expected != NULL);
+
+        if (check_frames(path, src, src_size, expected_size) ||
+            check_decompress(path, src, src_size, expected, expected_size) ||
+            check_stream(path, src, src_size, expected, expected_size)) {
+            return 1;
+        }
+        free(src);
+        free(expected);
+    }
+
+    return 0;
+}
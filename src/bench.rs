@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{self, WrapErr};
+use zstd_decompressor::{
+    decoding_context::DecodingContext, dictionary::Dictionary, frame::Frame,
+    options::DecoderOptions, parsing::ForwardByteParser, timings::Timings,
+};
+
+/// Decode all the frames of `input` into `output`. Frame parsing is measured in the
+/// timings of `context` when they are set.
+fn decode(
+    input: &[u8],
+    context: &mut DecodingContext,
+    dictionary: Option<&Dictionary>,
+    options: DecoderOptions,
+    output: &mut Vec<u8>,
+) -> eyre::Result<()> {
+    output.clear();
+    let mut frames = ForwardByteParser::new(input).iter_with_options(options);
+
+    loop {
+        let time = Instant::now();
+        let Some(frame) = frames.next() else {
+            break;
+        };
+        let frame = frame?;
+        if let Some(timings) = &mut context.timings {
+            timings.frame_parsing += time.elapsed();
+        }
+
+        if let Frame::ZStandardFrame(frame) = frame {
+            frame.decode_with_context(context, dictionary, output)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Throughput in MB/s of decoding `size` bytes in `time`
+fn throughput(size: u64, time: Duration) -> f64 {
+    size as f64 / 1e6 / time.as_secs_f64()
+}
+
+fn print_phase(name: &str, size: u64, time: Duration, total: Duration) {
+    println!(
+        "  {name:<20} {:>6.1}% {:>10.1} MB/s",
+        100. * time.as_secs_f64() / total.as_secs_f64(),
+        throughput(size, time)
+    );
+}
+
+fn print_timings(size: u64, timings: &Timings) {
+    let total = timings.total();
+    print_phase("frame parsing", size, timings.frame_parsing, total);
+    print_phase("literals", size, timings.literals, total);
+    print_phase(
+        "sequences decoding",
+        size,
+        timings.sequences_decoding,
+        total,
+    );
+    print_phase(
+        "sequences execution",
+        size,
+        timings.sequences_execution,
+        total,
+    );
+    print_phase("checksum", size, timings.checksum, total);
+}
+
+/// Decode each file `iterations` times and print the decoding throughput, overall and
+/// for each phase
+pub fn run(
+    files: &[String],
+    iterations: usize,
+    dictionary: Option<&Dictionary>,
+    options: DecoderOptions,
+) -> eyre::Result<()> {
+    let mut context = DecodingContext::default();
+    let mut output = Vec::new();
+    let (mut total_size, mut total_time) = (0, Duration::ZERO);
+    let mut total_timings = Timings::default();
+
+    for file in files {
+        let input = std::fs::read(file).wrap_err_with(|| format!("cannot read {file}"))?;
+
+        // Overall throughput, with sequences executed as soon as they are decoded
+        context.timings = None;
+        let time = Instant::now();
+        for _ in 0..iterations {
+            decode(&input, &mut context, dictionary, options, &mut output)
+                .wrap_err_with(|| format!("cannot decode {file}"))?;
+        }
+        let time = time.elapsed();
+        let size = (output.len() * iterations) as u64;
+
+        // Throughput of each phase
+        context.timings = Some(Timings::default());
+        for _ in 0..iterations {
+            decode(&input, &mut context, dictionary, options, &mut output)?;
+        }
+        let timings = context.timings.take().unwrap_or_default();
+
+        println!(
+            "{file}: {} bytes, {iterations} runs, {:.1} MB/s",
+            output.len(),
+            throughput(size, time)
+        );
+        print_timings(size, &timings);
+
+        total_size += size;
+        total_time += time;
+        total_timings += timings;
+    }
+
+    if files.len() > 1 {
+        println!(
+            "total: {total_size} bytes, {:.1} MB/s",
+            throughput(total_size, total_time)
+        );
+        print_timings(total_size, &total_timings);
+    }
+
+    Ok(())
+}
@@ -1,15 +1,36 @@
 extern crate zstd_decompressor;
 
-use clap::{arg, command, Parser};
-use color_eyre::{self, eyre};
-use zstd_decompressor::{frame::Frame, parsing::ForwardByteParser};
+mod bench;
+mod mmap;
+
+use clap::{Parser, Subcommand};
+use color_eyre::{
+    self,
+    eyre::{self, ensure},
+};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+use zstd_decompressor::{
+    dictionary::Dictionary, frame::ChecksumPolicy, options::DecoderOptions,
+    parallel::ParallelDecompressor, parsing::ForwardByteParser, reader::ZstdReader,
+};
+
+use memmap2::MmapMut;
+
+use crate::mmap::Input;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     ///ZStandard file input, decompress it and output to stdout
     #[arg(required = true)]
-    filename: String,
+    filename: Option<String>,
 
     ///Dump information about frames instead of outputing the result
     #[arg(short, long)]
@@ -22,6 +43,79 @@ struct Args {
     ///Output Skippable frames as well
     #[arg(short, long, action)]
     print_skippable: bool,
+
+    ///Use the given dictionary to decompress the frames, files which are not formatted
+    ///dictionaries are used as raw content (e.g. with `zstd --patch-from`)
+    #[arg(short = 'D', long, value_names = ["dict"])]
+    dictionary: Option<String>,
+
+    ///Do not verify the content checksum of the frames
+    #[arg(long, action)]
+    no_check: bool,
+
+    ///Maximum window size accepted, e.g. `--memory=128MiB` for files compressed with
+    ///`--long=27` (8MiB by default)
+    #[arg(long, value_names = ["size"], value_parser = parse_size)]
+    memory: Option<u64>,
+
+    ///Fail once more than the given number of bytes have been decompressed
+    #[arg(long, value_names = ["size"], value_parser = parse_size)]
+    max_output: Option<u64>,
+
+    ///Fail on inputs with more than the given number of frames
+    #[arg(long, value_names = ["count"])]
+    max_frames: Option<usize>,
+
+    ///Fail on frames with more than the given number of blocks
+    #[arg(long, value_names = ["count"])]
+    max_blocks: Option<usize>,
+
+    ///Decode frames on the given number of threads, one per core with 0. Each frame is
+    ///decoded entirely in memory instead of being streamed.
+    #[arg(short = 'T', long, value_names = ["count"])]
+    threads: Option<usize>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    ///Decode each file several times and report the decoding throughput, overall and for
+    ///each phase
+    Bench {
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        ///Number of times each file is decoded
+        #[arg(short = 'n', long, default_value_t = 10)]
+        iterations: usize,
+
+        ///Use the given dictionary to decompress the frames
+        #[arg(short = 'D', long, value_names = ["dict"])]
+        dictionary: Option<String>,
+
+        ///Maximum window size accepted (8MiB by default)
+        #[arg(long, value_names = ["size"], value_parser = parse_size)]
+        memory: Option<u64>,
+    },
+}
+
+/// Parse a size in bytes with an optional unit suffix (KiB, MiB, GiB, KB, MB, GB)
+fn parse_size(arg: &str) -> Result<u64, String> {
+    let split = arg.find(|c: char| !c.is_ascii_digit()).unwrap_or(arg.len());
+    let (number, unit) = arg.split_at(split);
+    let number: u64 = number.parse().map_err(|_| format!("invalid size: {arg}"))?;
+    let multiplier: u64 = match unit {
+        "" | "B" => 1,
+        "K" | "KiB" => 1 << 10,
+        "M" | "MiB" => 1 << 20,
+        "G" | "GiB" => 1 << 30,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        _ => return Err(format!("unknown size unit: {unit}")),
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size too large: {arg}"))
 }
 
 fn main() -> eyre::Result<()> {
@@ -29,32 +123,137 @@ fn main() -> eyre::Result<()> {
 
     let args = Args::parse();
 
-    let file = std::fs::read(args.filename)?;
-    let parser = ForwardByteParser::new(file.as_slice());
+    if let Some(Command::Bench {
+        files,
+        iterations,
+        dictionary,
+        memory,
+    }) = args.command
+    {
+        let dictionary = match dictionary {
+            Some(filename) => Some(Dictionary::load(std::fs::read(filename)?)?),
+            None => None,
+        };
+        let mut options = DecoderOptions::new();
+        if let Some(size) = memory {
+            options = options.max_window_size(size);
+        }
+        return bench::run(&files, iterations, dictionary.as_ref(), options);
+    }
+    let filename = args
+        .filename
+        .expect("filename is required without a subcommand");
+
+    let mut options = DecoderOptions::new().include_skippable(args.print_skippable);
+    if let Some(size) = args.memory {
+        options = options.max_window_size(size);
+    }
+    if let Some(size) = args.max_output {
+        options = options.max_output(size);
+    }
+    if let Some(count) = args.max_frames {
+        options = options.max_frames(count);
+    }
+    if let Some(count) = args.max_blocks {
+        options = options.max_blocks(count);
+    }
+
+    let input = Input::open(&filename)?;
 
     if args.info {
-        for frame in parser.iter() {
+        let parser = ForwardByteParser::new(&input);
+        for frame in parser.iter_with_options(options.include_skippable(true)) {
             println!("{:#x?}", frame?);
         }
         return Ok(());
     }
 
-    let mut res: Vec<u8> = vec![];
-    for frame in parser.iter() {
-        match frame {
-            Ok(Frame::SkippableFrame(skippable)) => {
-                if args.print_skippable {
-                    res.append(&mut Frame::SkippableFrame(skippable).decode()?)
+    let checksum_policy = if args.no_check {
+        ChecksumPolicy::Ignore
+    } else {
+        ChecksumPolicy::Verify
+    };
+    let dictionary = match args.dictionary {
+        Some(filename) => Some(Dictionary::load(std::fs::read(filename)?)?),
+        None => None,
+    };
+    let decoder = Decoder {
+        threads: args.threads.unwrap_or(1),
+        options,
+        checksum_policy,
+        dictionary,
+    };
+
+    match args.output {
+        Some(filename) => {
+            // Write directly to a mapped file when the size of the output is known
+            let size = mmap::content_size(&input, options)
+                .filter(|&size| size > 0 && options.check_output(size).is_ok());
+            match size {
+                Some(size) => {
+                    let mut output = mmap::create_output(&filename, size)?;
+                    let result = decoder.decompress_mapped(&input, &mut output);
+                    // Do not leave a preallocated file full of zeros behind
+                    if result.is_err() {
+                        std::fs::remove_file(&filename)?;
+                    }
+                    result?;
+                }
+                None => {
+                    let mut output = BufWriter::new(File::create(filename)?);
+                    decoder.decompress(&input, &mut output)?;
+                    output.flush()?;
                 }
             }
-            Ok(frame) => res.append(&mut frame.decode()?),
-            Err(e) => return Err(e.into()),
+        }
+        None => {
+            let mut output = io::stdout().lock();
+            decoder.decompress(&input, &mut output)?;
+            output.flush()?;
         }
     }
-    if args.output.is_some() {
-        std::fs::write(args.output.unwrap(), String::from_utf8(res).unwrap())?;
-    } else {
-        print!("{}", String::from_utf8(res).unwrap());
-    }
+
     Ok(())
 }
+
+/// Settings of the decompression of the input file
+struct Decoder {
+    threads: usize,
+    options: DecoderOptions,
+    checksum_policy: ChecksumPolicy,
+    dictionary: Option<Dictionary>,
+}
+
+impl Decoder {
+    /// Decompress `input` into `output`, streamed or on several threads
+    fn decompress<W: Write>(self, input: &[u8], output: &mut W) -> eyre::Result<()> {
+        if self.threads != 1 {
+            let mut decompressor = ParallelDecompressor::new(self.threads)
+                .with_options(self.options)
+                .with_checksum_policy(self.checksum_policy);
+            if let Some(dictionary) = self.dictionary {
+                decompressor = decompressor.with_dictionary(dictionary);
+            }
+            decompressor.decompress_to(input, output)?;
+        } else {
+            let mut decoder = ZstdReader::new(input)
+                .with_options(self.options)
+                .with_checksum_policy(self.checksum_policy);
+            if let Some(dictionary) = self.dictionary {
+                decoder = decoder.with_dictionary(dictionary);
+            }
+            io::copy(&mut decoder, output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Decompress `input` into `output`, whose size is the size of the decompressed content
+    fn decompress_mapped(self, input: &[u8], output: &mut MmapMut) -> eyre::Result<()> {
+        let mut remaining = &mut output[..];
+        self.decompress(input, &mut remaining)?;
+        ensure!(remaining.is_empty(), "decompressed content is too small");
+
+        Ok(output.flush()?)
+    }
+}
@@ -0,0 +1,98 @@
+use std::{fs::File, ops::Deref};
+
+use color_eyre::eyre;
+use memmap2::{Mmap, MmapMut};
+use zstd_decompressor::{
+    block::Block,
+    frame::{Header, MAGIC_SKIP, MAGIC_ZSTD},
+    options::DecoderOptions,
+    parsing::ForwardByteParser,
+};
+
+/// Content of an input file, mapped in memory when possible
+pub enum Input {
+    Mapped(Mmap),
+    /// Files which cannot be mapped, e.g. pipes, are read entirely
+    Read(Vec<u8>),
+}
+
+impl Input {
+    pub fn open(filename: &str) -> eyre::Result<Self> {
+        let file = File::open(filename)?;
+        // SAFETY: the file must not be modified while it is mapped, as for any mmap'd input
+        match unsafe { Mmap::map(&file) } {
+            Ok(map) => Ok(Input::Mapped(map)),
+            Err(_) => Ok(Input::Read(std::fs::read(filename)?)),
+        }
+    }
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Input::Mapped(map) => map,
+            Input::Read(data) => data,
+        }
+    }
+}
+
+/// Total size of the decompressed content of `input`, if every frame announces it. Only the
+/// frame and block headers are parsed, the content of the blocks is skipped.
+pub fn content_size(input: &[u8], options: DecoderOptions) -> Option<u64> {
+    let mut parser = ForwardByteParser::new(input);
+    let mut size = 0u64;
+    while !parser.is_empty() {
+        let frame_size = match parser.le_u32().ok()? {
+            MAGIC_ZSTD => skip_frame(&mut parser)?,
+            magic if magic ^ MAGIC_SKIP <= 0x0F => {
+                let len = parser.le_u32().ok()? as usize;
+                parser.slice(len).ok()?;
+                if options.include_skippable {
+                    len as u64
+                } else {
+                    0
+                }
+            }
+            _ => return None,
+        };
+        size = size.checked_add(frame_size)?;
+    }
+
+    Some(size)
+}
+
+/// Skip the ZStandard frame following its magic number, returning its announced content size
+fn skip_frame(parser: &mut ForwardByteParser) -> Option<u64> {
+    let header = Header::parse(parser).ok()?;
+    let content_size = header.content_size?;
+    loop {
+        let (last, block_type, block_size) = Block::parse_header(parser).ok()?;
+        let size = Block::content_size(block_type, block_size, header.block_maximum_size()).ok()?;
+        parser.slice(size).ok()?;
+        if last {
+            break;
+        }
+    }
+    if header.content_checksum_flag {
+        parser.slice(4).ok()?;
+    }
+
+    Some(content_size)
+}
+
+/// Create `filename` with `size` bytes and map it in memory, so that the output is
+/// written directly to the file
+pub fn create_output(filename: &str, size: u64) -> eyre::Result<MmapMut> {
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(filename)?;
+    file.set_len(size)?;
+
+    // SAFETY: the file was just created by us and is not modified by anything else
+    Ok(unsafe { MmapMut::map_mut(&file)? })
+}
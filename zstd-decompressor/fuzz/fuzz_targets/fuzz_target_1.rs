@@ -1,30 +1,24 @@
 #![no_main]
-#[macro_use]
-extern crate libfuzzer_sys;
-
 extern crate zstd_decompressor;
+
+use std::io::Read;
+
 use libfuzzer_sys::fuzz_target;
+use zstd_decompressor::{frame::Frame, parsing::ForwardByteParser, reader::ZstdReader};
 
+// Decoding must return an error on malformed input, never panic
 fuzz_target!(|data: &[u8]| {
-    println!("New data, len: {}", data.len());
-    let parser = zstd_decompressor::parsing::ForwardByteParser::new(data);
-
-    for frame in parser.iter() {
+    for frame in ForwardByteParser::new(data).iter() {
         match frame {
-            Ok(zstd_decompressor::frame::Frame::SkippableFrame(skippable)) => {
-                // if args.print_skippable {
-                //     res.push(
-                //         &mut zstd_decompressor::frame::Frame::SkippableFrame(skippable).decode(),
-                //     )
-                // }
-            }
-            Ok(frame) => {
-                &mut frame.decode();
-            }
-            Err(e) => {
-                dbg!(e);
-                break;
+            Ok(frame @ Frame::ZStandardFrame(_)) => {
+                let _ = frame.decode();
             }
+            Ok(Frame::SkippableFrame(_)) => (),
+            Err(_) => break,
         }
     }
+
+    let _ = ZstdReader::new(data)
+        .include_skippable(true)
+        .read_to_end(&mut vec![]);
 });
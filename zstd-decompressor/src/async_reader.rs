@@ -0,0 +1,151 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::io::{AsyncBufRead, AsyncRead, ReadBuf};
+
+use crate::{
+    dictionary::Dictionary, frame::ChecksumPolicy, options::DecoderOptions, reader::invalid_data,
+    stream::StreamDecoder,
+};
+
+/// Maximum number of compressed bytes decoded in a single poll, so that a large input
+/// buffer does not block the executor
+const MAX_FEED_SIZE: usize = 1 << 16;
+
+/// Asynchronous decoder reading the compressed input from a tokio `AsyncBufRead`.
+///
+/// Each poll decodes at most `MAX_FEED_SIZE` bytes of input, and stops after the block
+/// which fills the read buffer, so at most one block more than requested is kept.
+/// Incomplete blocks are kept until the reader provides the rest. `R` must be `Unpin`,
+/// readers which are not can be pinned with `Box::pin`.
+pub struct AsyncZstdDecoder<R> {
+    reader: R,
+    decoder: StreamDecoder,
+    /// Decoded content not read yet
+    decoded: Vec<u8>,
+    /// Position of the next byte to output in `decoded`
+    pos: usize,
+    /// The underlying reader is exhausted
+    done: bool,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncZstdDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        AsyncZstdDecoder {
+            reader,
+            decoder: StreamDecoder::new(),
+            decoded: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+
+    /// Set the limits of the decoder
+    pub fn with_options(mut self, options: DecoderOptions) -> Self {
+        self.decoder = self.decoder.with_options(options);
+        self
+    }
+
+    /// Decode frames starting from `dictionary`
+    pub fn with_dictionary(mut self, dictionary: Dictionary) -> Self {
+        self.decoder = self.decoder.with_dictionary(dictionary);
+        self
+    }
+
+    /// Set what to do when the content checksum of a frame does not match
+    pub fn with_checksum_policy(mut self, policy: ChecksumPolicy) -> Self {
+        self.decoder = self.decoder.with_checksum_policy(policy);
+        self
+    }
+
+    /// Get back the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for AsyncZstdDecoder<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.pos == this.decoded.len() && !this.done {
+            this.decoded.clear();
+            this.pos = 0;
+
+            let input = ready!(Pin::new(&mut this.reader).poll_fill_buf(cx))?;
+            if input.is_empty() {
+                this.done = true;
+                this.decoder.finish().map_err(invalid_data)?;
+            } else {
+                let len = input.len().min(MAX_FEED_SIZE);
+                let max_output = buf.remaining().max(1) as u64;
+                let result =
+                    this.decoder
+                        .feed_bounded(&input[..len], &mut this.decoded, max_output);
+                let consumed = result.as_ref().map_or(len, |progress| progress.consumed);
+                Pin::new(&mut this.reader).consume(consumed);
+                result.map_err(invalid_data)?;
+
+                // Headers only, decode more in the next poll
+                if this.decoded.is_empty() {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+            }
+        }
+
+        let available = &this.decoded[this.pos..];
+        let len = available.len().min(buf.remaining());
+        buf.put_slice(&available[..len]);
+        this.pos += len;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::AsyncZstdDecoder;
+    use crate::block::MAX_BLOCK_SIZE;
+
+    /// Frame of `blocks` RLE blocks of 128KiB, 4 bytes each
+    fn rle_frame(blocks: usize) -> Vec<u8> {
+        // Magic number, frame header descriptor and a 128KiB window
+        let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd, 0x00, 0x38];
+        for index in 0..blocks {
+            let last = (index + 1 == blocks) as u32;
+            let header = (MAX_BLOCK_SIZE as u32) << 3 | 1 << 1 | last;
+            frame.extend_from_slice(&header.to_le_bytes()[..3]);
+            frame.push(b'a');
+        }
+        frame
+    }
+
+    #[tokio::test]
+    async fn decoded_output_bounded() {
+        let input = rle_frame(64);
+        let mut decoder = AsyncZstdDecoder::new(&input[..]);
+
+        let mut buf = [0; 1000];
+        let mut total = 0;
+        loop {
+            let read = decoder.read(&mut buf).await.unwrap();
+            assert!(decoder.decoded.len() <= MAX_BLOCK_SIZE);
+            if read == 0 {
+                break;
+            }
+            assert!(buf[..read].iter().all(|&byte| byte == b'a'));
+            total += read;
+        }
+        assert_eq!(64 * MAX_BLOCK_SIZE, total);
+    }
+}
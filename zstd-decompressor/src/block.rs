@@ -1,30 +1,31 @@
+use alloc::vec::Vec;
+use core::{hash::Hasher, mem};
+#[cfg(feature = "std")]
+use std::time::Instant;
+
 use crate::{
-    decoding_context::{self, DecodingContext},
-    literals::{self, LiteralsSection},
+    decoding_context::DecodingContext,
+    literals::LiteralsSection,
     parsing::{ForwardBitParser, ForwardByteParser},
-    sequences::{self, Sequences},
+    sequences::Sequences,
+    Section,
 };
 
-use eyre;
-use thiserror;
-
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error{"Block is reserved"}]
     ReservedBlockType(),
-    #[error{"Parsing error: {0}"}]
-    ParsingError(#[from] crate::parsing::Error),
-    #[error{"Block decoded size exceeds maximum accepted size."}]
-    LargeBlockSize,
-    #[error{"Error in literals section: {0}"}]
-    LiteralsSectionError(#[from] literals::Error),
-    #[error{"Error in sequences section: {0}"}]
-    SequencesSectionError(#[from] sequences::Error),
-    #[error{"Decoding context error: {0}"}]
-    DecodingContextError(#[from] decoding_context::Error),
+    #[error{"Block size {got} exceeds the maximum block size {max}"}]
+    BlockSizeTooBig { max: usize, got: usize },
+    #[error{"Block decoded size {got} exceeds the maximum block size {max}"}]
+    LargeBlockSize { max: usize, got: usize },
 }
 
-type Result<T> = eyre::Result<T, Error>;
+type Result<T> = core::result::Result<T, crate::Error>;
+
+/// Maximum size of the decoded content of a block, see
+/// https://datatracker.ietf.org/doc/html/rfc8878#name-block_maximum_size
+pub const MAX_BLOCK_SIZE: usize = 128 << 10;
 
 #[derive(Debug)]
 pub enum Block<'a> {
@@ -40,8 +41,15 @@ pub enum Block<'a> {
 }
 
 impl<'a> Block<'a> {
-    pub fn parse(parser: &mut ForwardByteParser<'a>) -> Result<(Block<'a>, bool)> {
-        let header = parser.slice(3)?;
+    /// Parse a 3 bytes block header, return the last block flag, the block type and the
+    /// block size
+    pub fn parse_header(parser: &mut ForwardByteParser<'a>) -> Result<(bool, u64, usize)> {
+        let start = parser.remaining();
+        let header = parser.slice(3).map_err(|e| {
+            crate::Error::from(e)
+                .in_section(Section::BlockHeader)
+                .at(start)
+        })?;
 
         let mut header_parser = ForwardBitParser::new(header).unwrap();
 
@@ -49,6 +57,47 @@ impl<'a> Block<'a> {
         let block_type = header_parser.take(2).unwrap();
         let block_size = header_parser.take(header_parser.len()).unwrap() as usize;
 
+        Ok((last_block, block_type, block_size))
+    }
+
+    /// Return the number of bytes following the block header given the block type and
+    /// the block size, or an error if the block type is reserved or the block size is
+    /// larger than `max_block_size`
+    pub fn content_size(
+        block_type: u64,
+        block_size: usize,
+        max_block_size: usize,
+    ) -> Result<usize> {
+        if block_size > max_block_size {
+            return Err(crate::Error::from(Error::BlockSizeTooBig {
+                max: max_block_size,
+                got: block_size,
+            })
+            .in_section(Section::BlockHeader));
+        }
+
+        match block_type {
+            0 | 2 => Ok(block_size),
+            1 => Ok(1),
+            _ => {
+                Err(crate::Error::from(Error::ReservedBlockType()).in_section(Section::BlockHeader))
+            }
+        }
+    }
+
+    pub fn parse(parser: &mut ForwardByteParser<'a>) -> Result<(Block<'a>, bool)> {
+        Self::parse_with_max_size(parser, MAX_BLOCK_SIZE)
+    }
+
+    /// Parse a block, failing if its size is larger than `max_block_size`, usually the
+    /// `Header::block_maximum_size` of the frame
+    pub fn parse_with_max_size(
+        parser: &mut ForwardByteParser<'a>,
+        max_block_size: usize,
+    ) -> Result<(Block<'a>, bool)> {
+        let (last_block, block_type, block_size) = Self::parse_header(parser)?;
+        Self::content_size(block_type, block_size, max_block_size)?;
+
         Ok((
             match block_type {
                 // RawBlock
@@ -65,35 +114,107 @@ impl<'a> Block<'a> {
                         sequences_section: Sequences::parse(&mut new_parser)?,
                     }
                 }
-                _ => return Err(Error::ReservedBlockType()),
+                _ => return Err(Error::ReservedBlockType().into()),
             },
             last_block,
         ))
     }
 
+    /// Decode the block, appending its content to `context.decoded`, counting it in
+    /// `context.produced` and feeding it to the checksum of `context` if any. Fail if the
+    /// decoded content is larger than the maximum block size of the frame.
     pub fn decode(self, context: &mut DecodingContext) -> Result<()> {
+        let start = context.decoded.len();
+
         match self {
-            Self::RawBlock(a) => context.decoded.append(&mut Vec::from(a)),
+            Self::RawBlock(a) => {
+                context.decoded.check_space(a.len())?;
+                context.decoded.extend_from_slice(a);
+            }
             Self::RLEBlock { byte, repeat } => {
-                context.decoded.append(&mut vec![byte; repeat as usize])
+                context.decoded.check_space(repeat as usize)?;
+                context.decoded.extend_repeated(byte, repeat as usize);
             }
             Self::CompressedBlock {
                 literals_section,
                 sequences_section,
             } => {
-                let literals = literals_section.decode(context)?;
-                let seq = sequences_section.decode(context)?;
-                context.execute_sequences(seq, &literals)?;
+                // Reuse the literals buffer of the context, it is only lost on error
+                let mut literals = mem::take(&mut context.literals);
+                // Only reserve what the block can still decode, so that the output
+                // preallocated for the content size is not reallocated
+                context.decoded.reserve(context.next_block_maximum_size());
+                Self::decode_sections(context, literals_section, sequences_section, &mut literals)?;
+                context.literals = literals;
             }
         };
 
-        // if decoded.len() as u64 > context.window_size {
-        //     return Err(Error::LargeBlockSize);
-        // } else if decoded.len() + context.decoded.len() > context.window_size as usize {
-        //     for _ in 0..decoded.len() + context.decoded.len() - context.window_size as usize {
-        //         context.decoded.remove(0);
-        //     }
-        // }
+        let size = context.decoded.len() - start;
+        let max = context.block_maximum_size();
+        if size > max {
+            return Err(Error::LargeBlockSize { max, got: size }.into());
+        }
+
+        context.produced += size as u64;
+        if let Some(checksum) = &mut context.checksum {
+            // The clock is only read when measuring
+            #[cfg(feature = "std")]
+            let time = context.timings.is_some().then(Instant::now);
+            checksum.write(&context.decoded[start..]);
+            #[cfg(feature = "std")]
+            if let (Some(time), Some(timings)) = (time, &mut context.timings) {
+                timings.checksum += time.elapsed();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decode the sections of a compressed block into `context`, measuring them if
+    /// `context.timings` is set
+    fn decode_sections(
+        context: &mut DecodingContext,
+        literals_section: LiteralsSection,
+        sequences_section: Sequences,
+        literals: &mut Vec<u8>,
+    ) -> Result<()> {
+        #[cfg(feature = "std")]
+        if context.timings.is_some() {
+            return Self::decode_timed(context, literals_section, sequences_section, literals);
+        }
+
+        literals_section.decode_into(context, literals)?;
+        sequences_section.execute(context, literals)
+    }
+
+    /// Decode the sections of a compressed block, measuring each phase in
+    /// `context.timings`. Sequences are all decoded before being executed.
+    #[cfg(feature = "std")]
+    fn decode_timed(
+        context: &mut DecodingContext,
+        literals_section: LiteralsSection,
+        sequences_section: Sequences,
+        literals: &mut Vec<u8>,
+    ) -> Result<()> {
+        let time = Instant::now();
+        literals_section.decode_into(context, literals)?;
+        let literals_time = time.elapsed();
+
+        let time = Instant::now();
+        let sequences = sequences_section.decode(context)?;
+        let decoding_time = time.elapsed();
+
+        let time = Instant::now();
+        context
+            .execute_sequences(&sequences, literals)
+            .map_err(|e| crate::Error::from(e).in_section(Section::SequenceBitstream))?;
+        let execution_time = time.elapsed();
+
+        if let Some(timings) = &mut context.timings {
+            timings.literals += literals_time;
+            timings.sequences_decoding += decoding_time;
+            timings.sequences_execution += execution_time;
+        }
 
         Ok(())
     }
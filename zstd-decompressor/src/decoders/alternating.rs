@@ -5,26 +5,26 @@ use super::{
     BitDecoder, Result,
 };
 
-pub struct AlternatingDecoder {
-    pub first_decoder: FseDecoder,
-    pub second_decoder: FseDecoder,
+pub struct AlternatingDecoder<'t> {
+    pub first_decoder: FseDecoder<'t>,
+    pub second_decoder: FseDecoder<'t>,
     pub last_updated_is_first: bool,
     pub last_read_is_first: bool,
 }
 
-impl AlternatingDecoder {
-    pub fn new(table: FseTable) -> Self {
-        let bis_table = table.clone();
+impl<'t> AlternatingDecoder<'t> {
+    /// Two decoders sharing `table`
+    pub fn new(table: &'t FseTable) -> Self {
         AlternatingDecoder {
             first_decoder: FseDecoder::new_from_table(table),
-            second_decoder: FseDecoder::new_from_table(bis_table),
+            second_decoder: FseDecoder::new_from_table(table),
             last_updated_is_first: false,
             last_read_is_first: false,
         }
     }
 }
 
-impl BitDecoder<u16> for AlternatingDecoder {
+impl BitDecoder<u16> for AlternatingDecoder<'_> {
     fn initialize(&mut self, bitstream: &mut BackwardBitParser) -> Result<()> {
         self.first_decoder.initialize(bitstream)?;
         self.second_decoder.initialize(bitstream)?;
@@ -1,8 +1,9 @@
 use crate::{
     parsing::{BackwardBitParser, ForwardBitParser},
-    utils::{discrete_log2, min_bits_required},
+    utils::discrete_log2,
 };
-use std::{
+use alloc::{format, vec, vec::Vec};
+use core::{
     fmt::Debug,
     fmt::Display,
     ops::{Index, IndexMut},
@@ -75,13 +76,6 @@ pub struct State {
     pub bits_to_read: u16,
 }
 
-#[derive(Clone, Debug)]
-struct TmpState {
-    output: u16,
-    baseline: Option<u16>,
-    bits_to_read: Option<u16>,
-}
-
 #[derive(Clone, Debug, PartialEq)]
 pub struct FseTable {
     pub table: Vec<State>,
@@ -89,7 +83,7 @@ pub struct FseTable {
 }
 
 impl Display for FseTable {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         writeln!(f, "State Symbol   BL NB")?;
         self.table.iter().enumerate().for_each(|(pos, s)| {
             writeln!(
@@ -107,100 +101,136 @@ impl Display for FseTable {
 }
 
 impl FseTable {
+    /// Build the decoding table in linear time, see
+    /// https://datatracker.ietf.org/doc/html/rfc8878#name-fse-table-description
     pub fn from_distribution(accuracy_log: u8, distribution: &[i16]) -> Result<Self> {
         if accuracy_log > MAX_AL {
             return Err(Error::LargeAccuracyLog(accuracy_log));
         }
 
-        let num_symbol = distribution.len();
         let table_size = 1 << accuracy_log;
-        let mut tmp_table: Vec<Option<TmpState>> = vec![None; table_size];
-
-        // fill last elements with less than one probability symbols
-        let mut zero_pos = table_size;
-        distribution
+        // Less than one probability symbols count as 1 and every state must be used once
+        let total = distribution
             .iter()
-            .enumerate()
-            .filter(|(_, &v)| v == -1) // Only retrieve less than one proba symbols
-            // .rev() // DO NOT Reverse to have them in the same order as the end of tmp_table
-            .for_each(|(s, _)| {
-                zero_pos -= 1;
-                tmp_table[zero_pos] = Some(TmpState {
-                    output: s as u16,
-                    baseline: Some(0),
-                    bits_to_read: Some(accuracy_log as u16),
-                });
-            });
-
-        // Fill remaining spots with the non zero probability states
+            .try_fold(0usize, |total, &proba| match proba {
+                -1 => Ok(total + 1),
+                p if p >= 0 => Ok(total + p as usize),
+                _ => Err(Error::CorruptedTable),
+            })?;
+        if total != table_size || distribution.len() > MAX_SYMBOL {
+            return Err(Error::CorruptedTable);
+        }
+
+        let mut symbols = vec![0u16; table_size];
+
+        // Less than one probability symbols fill the end of the table
+        let mut high_threshold = table_size;
+        for (symbol, _) in distribution.iter().enumerate().filter(|(_, &p)| p == -1) {
+            high_threshold -= 1;
+            symbols[high_threshold] = symbol as u16;
+        }
+
+        // Spread the other symbols, skipping the end of the table
         let mut position = 0;
         let step = (table_size >> 1) + (table_size >> 3) + 3;
         let mask = table_size - 1;
-        distribution
-            .iter()
-            .enumerate()
-            .filter(|(_, &v)| v > 0)
-            .for_each(|(symbol, &proba)| {
-                for _ in 0..proba {
-                    // each symbol is represented proba times in the table
-                    tmp_table[position] = Some(TmpState {
-                        output: symbol as u16,
-                        baseline: None,
-                        bits_to_read: None,
-                    });
-
+        for (symbol, &proba) in distribution.iter().enumerate().filter(|(_, &p)| p > 0) {
+            for _ in 0..proba {
+                symbols[position] = symbol as u16;
+                position = (position + step) & mask;
+                while position >= high_threshold {
                     position = (position + step) & mask;
-                    while position >= zero_pos {
-                        position = (position + step) & mask;
-                    }
                 }
-            });
+            }
+        }
 
-        // remove options
-        let mut tmp_table = tmp_table
+        // The states of a symbol, in table order, count up from its probability. A state
+        // reads enough bits to reach the next power of 2 of the table size.
+        let mut next: Vec<u16> = distribution.iter().map(|&p| p.unsigned_abs()).collect();
+        let table = symbols
             .into_iter()
-            .map(|v| match v {
-                None => Err(Error::CorruptedTable),
-                Some(s) => Ok(s),
+            .map(|symbol| {
+                let state = next[symbol as usize];
+                next[symbol as usize] += 1;
+                let bits_to_read = accuracy_log as u16 - state.ilog2() as u16;
+
+                State {
+                    output: symbol,
+                    baseline: (state << bits_to_read) - table_size as u16,
+                    bits_to_read,
+                }
             })
-            .collect::<Result<Vec<TmpState>>>()?;
-
-        // Compute baseline and bits_to_read for non-zero proba symbols
-        for symbol in 0..num_symbol as u16 {
-            let mut grouped: Vec<_> = tmp_table
-                .iter_mut()
-                .filter(|s| s.output == symbol)
-                .collect();
-            let num_states = grouped.len();
-            let parts = 1 << (f32::log2(num_states as f32).ceil() as u32);
-            let base_width = table_size / parts;
-            let base_nb = discrete_log2(base_width) as u8;
-            let mut baseline = 0;
-
-            for i in parts - num_states..parts {
-                let new_i = i % num_states;
-                let (add, mult) = if new_i != i { (1, 2) } else { (0, 1) };
-
-                grouped[new_i].bits_to_read = Some((base_nb + add) as u16);
-                grouped[new_i].baseline = Some(baseline);
-
-                baseline += base_width as u16 * mult;
-            }
-        }
+            .collect();
 
         Ok(FseTable {
-            table: tmp_table
-                .iter()
-                .map(|tmp_s| State {
-                    output: tmp_s.output,
-                    bits_to_read: tmp_s.bits_to_read.unwrap(),
-                    baseline: tmp_s.baseline.unwrap(),
-                })
-                .collect(),
+            table,
             al: accuracy_log,
         })
     }
 
+    /// States of the table of `N` entries for a valid `distribution`, built like
+    /// `from_distribution` but at compile time
+    pub(crate) const fn const_states<const N: usize>(distribution: &[i16]) -> [State; N] {
+        const EMPTY: State = State {
+            output: 0,
+            baseline: 0,
+            bits_to_read: 0,
+        };
+        let accuracy_log = N.ilog2() as u16;
+
+        let mut symbols = [0u16; N];
+        let mut high_threshold = N;
+        let mut symbol = 0;
+        while symbol < distribution.len() {
+            if distribution[symbol] == -1 {
+                high_threshold -= 1;
+                symbols[high_threshold] = symbol as u16;
+            }
+            symbol += 1;
+        }
+
+        let mut position = 0;
+        let step = (N >> 1) + (N >> 3) + 3;
+        let mask = N - 1;
+        symbol = 0;
+        while symbol < distribution.len() {
+            let mut count = 0;
+            while count < distribution[symbol] {
+                symbols[position] = symbol as u16;
+                position = (position + step) & mask;
+                while position >= high_threshold {
+                    position = (position + step) & mask;
+                }
+                count += 1;
+            }
+            symbol += 1;
+        }
+
+        let mut next = [0u16; MAX_SYMBOL];
+        symbol = 0;
+        while symbol < distribution.len() {
+            next[symbol] = distribution[symbol].unsigned_abs();
+            symbol += 1;
+        }
+
+        let mut states = [EMPTY; N];
+        let mut index = 0;
+        while index < N {
+            let symbol = symbols[index];
+            let state = next[symbol as usize];
+            next[symbol as usize] += 1;
+            let bits_to_read = accuracy_log - state.ilog2() as u16;
+            states[index] = State {
+                output: symbol,
+                baseline: (state << bits_to_read) - N as u16,
+                bits_to_read,
+            };
+            index += 1;
+        }
+
+        states
+    }
+
     pub fn parse(input: &mut ForwardBitParser) -> Result<Self> {
         let (al, distribution) = parse_fse_table(input)?;
 
@@ -227,37 +257,29 @@ impl IndexMut<usize> for FseTable {
 }
 
 #[derive(PartialEq, Debug, Clone)]
-pub struct FseDecoder {
-    table: FseTable,
+pub struct FseDecoder<'t> {
+    /// States of the borrowed table, kept as a slice to avoid an indirection
+    states: &'t [State],
+    al: u8,
     cur_state: usize,
-    next_symbol_s: u8,
     next_symbol: Option<u16>,
 }
 
-impl FseDecoder {
-    pub fn new(input: &mut ForwardBitParser) -> Result<Self> {
-        let table = FseTable::parse(input)?;
-        let cur_state = 0;
-        let next_symbol = None;
-        let next_symbol_s = 0;
-
-        Ok(FseDecoder {
-            table,
-            cur_state,
-            next_symbol_s,
-            next_symbol,
-        })
+impl<'t> FseDecoder<'t> {
+    /// Decoder borrowing `table`, which can be shared by several decoders and blocks
+    pub fn new_from_table(table: &'t FseTable) -> Self {
+        Self::new_from_states(&table.table)
     }
 
-    pub fn new_from_table(table: FseTable) -> Self {
+    /// Decoder borrowing the states of a table, whose number is a power of 2
+    pub fn new_from_states(states: &'t [State]) -> Self {
         let cur_state = 0;
         let next_symbol = None;
-        let next_symbol_s = 0;
 
         FseDecoder {
-            table,
+            states,
+            al: states.len().ilog2() as u8,
             cur_state,
-            next_symbol_s,
             next_symbol,
         }
     }
@@ -265,30 +287,27 @@ impl FseDecoder {
     // pub fn from(table: FseTable) -> Self {
     //     let cur_state = 0;
     //     let next_symbol = None;
-    //     let next_symbol_s = 0;
 
     //     FseDecoder {
     //         table,
     //         cur_state,
-    //         next_symbol_s,
     //         next_symbol,
     //     }
     // }
 }
 
-impl BitDecoder<u16> for FseDecoder {
+impl BitDecoder<u16> for FseDecoder<'_> {
     fn initialize(&mut self, bitstream: &mut BackwardBitParser) -> Result<()> {
-        let state = bitstream.take(self.table.al() as usize)? as usize;
+        let state = bitstream.take(self.al as usize)? as usize;
 
-        self.next_symbol = Some(self.table[state].output);
-        self.next_symbol_s = min_bits_required(self.next_symbol.unwrap());
+        self.next_symbol = Some(self.states[state].output);
         self.cur_state = state;
 
         Ok(())
     }
 
     fn expected_bits(&self) -> usize {
-        self.table[self.cur_state].bits_to_read as usize
+        self.states[self.cur_state].bits_to_read as usize
     }
 
     fn symbol(&mut self) -> u16 {
@@ -308,10 +327,9 @@ impl BitDecoder<u16> for FseDecoder {
         }
 
         let new_state = bitstream.take(self.expected_bits())? as usize
-            + self.table[self.cur_state].baseline as usize;
+            + self.states[self.cur_state].baseline as usize;
 
-        self.next_symbol = Some(self.table[new_state].output);
-        self.next_symbol_s = min_bits_required(self.next_symbol.unwrap());
+        self.next_symbol = Some(self.states[new_state].output);
         self.cur_state = new_state;
 
         Ok(false)
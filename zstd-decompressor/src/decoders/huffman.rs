@@ -1,4 +1,5 @@
-use std::fmt::{self, Formatter};
+use alloc::{format, vec, vec::Vec};
+use core::fmt::{self, Formatter};
 
 use crate::{
     decoders::{alternating::AlternatingDecoder, fse::FseTable, BitDecoder},
@@ -6,70 +7,37 @@ use crate::{
     utils::discrete_log2,
 };
 
-use super::Result;
-
-#[derive(PartialEq)]
-pub enum HuffmanDecoder {
-    Absent,
-    Symbol {
-        payload: u8,
-    },
-    Tree {
-        left: Box<HuffmanDecoder>,
-        right: Box<HuffmanDecoder>,
-    },
-}
-
-struct HuffmanDecoderIterator<'a> {
-    noeuds: Vec<(&'a HuffmanDecoder, String)>,
-}
-
-impl Iterator for HuffmanDecoderIterator<'_> {
-    type Item = (HuffmanDecoder, String);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.noeuds.pop() {
-            Some((HuffmanDecoder::Symbol { payload: symb }, prefixe)) => {
-                Some((HuffmanDecoder::Symbol { payload: *symb }, prefixe))
-                // Tu recrées un élément, c'est pas super opti, tu peux direct renvoyer l'élément
-            }
-            Some((
-                HuffmanDecoder::Tree {
-                    left: gauche,
-                    right: droite,
-                },
-                mut prefixe,
-            )) => {
-                let mut old_prefix = prefixe.clone();
-                self.noeuds.push((droite, {
-                    prefixe.push('1');
-                    prefixe
-                }));
-                self.noeuds.push((gauche, {
-                    old_prefix.push('0');
-                    old_prefix
-                }));
-                self.next()
-            }
-            Some((HuffmanDecoder::Absent, _)) => self.next(),
-            None => None,
-        }
-    }
+use super::{Error, Result};
+
+/// Maximum number of bits of a Huffman prefix code, see
+/// https://datatracker.ietf.org/doc/html/rfc8878#name-huffman-tree-description
+const MAX_NUM_BITS: u8 = 11;
+
+/// Huffman decoding table, indexed by the next `max_bits` bits of the stream.
+///
+/// A symbol with a prefix code of `width` bits fills the `2^(max_bits - width)` entries
+/// starting with its prefix, so a single lookup gives both the symbol and its width.
+#[derive(PartialEq, Clone)]
+pub struct HuffmanDecoder {
+    max_bits: u8,
+    /// Symbol and prefix width of each entry, a width of 0 marks an unused prefix
+    table: Vec<(u8, u8)>,
 }
 
 impl fmt::Debug for HuffmanDecoder {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let tree_iter = HuffmanDecoderIterator {
-            noeuds: vec![(self, String::from(" "))],
-        };
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         let mut t = f.debug_struct("HuffmanDecoder");
-        for noeud in tree_iter {
-            match noeud.0 {
-                HuffmanDecoder::Symbol { payload } => {
-                    t.field(&noeud.1, &payload);
-                }
-                _ => unreachable!(),
+        let mut index = 0;
+        while index < self.table.len() {
+            let (symbol, width) = self.table[index];
+            if width == 0 {
+                index += 1;
+                continue;
             }
+            let shift = self.max_bits - width;
+            let prefix = format!(" {:0width$b}", index >> shift, width = width as usize);
+            t.field(&prefix, &symbol);
+            index += 1 << shift;
         }
 
         t.finish()
@@ -93,7 +61,7 @@ impl HuffmanDecoder {
         let data = input.slice(num_weights / 2 + num_weights % 2)?; // 2 weights per byte
 
         let mut res = vec![];
-        let mut parser = ForwardBitParser::new(data).unwrap();
+        let mut parser = ForwardBitParser::new(data)?;
         while !parser.is_empty() {
             let tmp = parser.take(4).unwrap() as u8;
             res.push(parser.take(4).unwrap() as u8);
@@ -108,17 +76,21 @@ impl HuffmanDecoder {
     fn parse_fse(input: &mut ForwardByteParser, compressed_size: u8) -> Result<Vec<u8>> {
         let data = input.slice(compressed_size as usize)?;
 
-        let mut parser = ForwardBitParser::new(data).unwrap();
+        let mut parser = ForwardBitParser::new(data)?;
 
         let fse_table = FseTable::parse(&mut parser)?;
 
         let mut bitstream = BackwardBitParser::new(&data[parser.bytes_read()..])?;
 
         let mut weights = Vec::new();
-        let mut decoder = AlternatingDecoder::new(fse_table);
+        let mut decoder = AlternatingDecoder::new(&fse_table);
         decoder.initialize(&mut bitstream)?;
 
         while decoder.expected_bits() <= bitstream.len() {
+            // States may read no bits at all, so a corrupted stream could loop forever
+            if weights.len() > 255 {
+                return Err(Error::InvalidHuffmanWeights);
+            }
             weights.push(decoder.symbol() as u8);
             decoder.update_bits(&mut bitstream)?;
         }
@@ -129,36 +101,12 @@ impl HuffmanDecoder {
         Ok(weights)
     }
 
-    pub fn insert(&mut self, symbol: u8, width: u8) -> bool {
-        if width == 0 {
-            match self {
-                HuffmanDecoder::Absent => {
-                    *self = HuffmanDecoder::Symbol { payload: symbol };
-                    true
-                }
-                _ => false,
-            }
-        } else {
-            match self {
-                HuffmanDecoder::Tree {
-                    left: gauche,
-                    right: droite,
-                } => gauche.insert(symbol, width - 1) || droite.insert(symbol, width - 1),
-                HuffmanDecoder::Absent => {
-                    *self = HuffmanDecoder::Tree {
-                        left: Box::new(HuffmanDecoder::Absent),
-                        right: Box::new(HuffmanDecoder::Absent),
-                    };
-                    self.insert(symbol, width)
-                }
-                HuffmanDecoder::Symbol { payload: _ } => {
-                    panic!("Trying to inster a symbol into another")
-                }
-            }
+    /// Build the table from the prefix width of each symbol, fail if the prefixes overlap
+    pub fn from_number_of_bits(numb_bytes: Vec<u8>) -> Result<HuffmanDecoder> {
+        if numb_bytes.len() > 256 || numb_bytes.iter().any(|&width| width > MAX_NUM_BITS) {
+            return Err(Error::InvalidHuffmanWeights);
         }
-    }
 
-    pub fn from_number_of_bits(numb_bytes: Vec<u8>) -> HuffmanDecoder {
         let mut symb: Vec<(u8, u8)> = vec![];
         for (i, item) in numb_bytes.iter().enumerate() {
             if item != &0 {
@@ -167,27 +115,49 @@ impl HuffmanDecoder {
         }
         symb.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0).reverse()));
         symb.reverse();
-        let mut res = HuffmanDecoder::Absent;
-        for item in symb {
-            res.insert(item.0, item.1);
+
+        // Longest prefixes come first and get the smallest codes
+        let max_bits = symb.first().map_or(0, |item| item.1);
+        let mut table = vec![(0, 0); 1 << max_bits];
+        let mut position = 0;
+        for (symbol, width) in symb {
+            let size = 1 << (max_bits - width);
+            position = usize::next_multiple_of(position, size);
+            if position + size > table.len() {
+                return Err(Error::OverlappingHuffmanCodes);
+            }
+            table[position..position + size].fill((symbol, width));
+            position += size;
         }
-        res
+
+        Ok(HuffmanDecoder { max_bits, table })
     }
 
     pub fn from_weights(weights: Vec<u8>) -> Result<HuffmanDecoder> {
+        // The last weight is implicit and there are at most 256 symbols
+        if weights.len() > 255 || weights.iter().any(|&poid| poid > MAX_NUM_BITS) {
+            return Err(Error::InvalidHuffmanWeights);
+        }
+
         let mut sum: u32 = 0; //Pour se souvenir de la somme des poids connus
         for &poid in weights.iter() {
             if poid != 0 {
                 sum += 1 << (poid - 1); //On calcule la somme
             }
         }
-        let mut puissance: u8 = discrete_log2(sum); //On calcule la puissance
-                                                    //ilog2 arrondit par valeur inférieure
-        if 1 << puissance < sum {
-            puissance += 1;
+        if sum == 0 {
+            return Err(Error::InvalidHuffmanWeights);
         }
-
-        let manquant: u8 = discrete_log2(((1u32 << puissance) - sum) as u8) + 1;
+        // Max_Number_of_Bits is highbit(sum) + 1: the implicit last weight completes the sum
+        // to the next power of 2, which is strictly greater than the sum
+        let puissance: u8 = discrete_log2(sum) + 1;
+
+        // The implicit last weight must be a power of 2
+        let reste = (1u32 << puissance) - sum;
+        if puissance > MAX_NUM_BITS || !reste.is_power_of_two() {
+            return Err(Error::InvalidHuffmanWeights);
+        }
+        let manquant: u8 = discrete_log2(reste) + 1;
 
         let mut prefixewidths: Vec<u8> = vec![];
         for poid in weights {
@@ -199,29 +169,30 @@ impl HuffmanDecoder {
         }
         prefixewidths.push(puissance + 1 - manquant);
 
-        Ok(Self::from_number_of_bits(prefixewidths))
+        Self::from_number_of_bits(prefixewidths)
+    }
+
+    /// Width of the longest prefix code
+    pub fn max_bits(&self) -> u8 {
+        self.max_bits
     }
 
     pub fn decode(&self, parser: &mut BackwardBitParser) -> Result<u8> {
-        match self {
-            HuffmanDecoder::Symbol { payload } => Ok(*payload),
-            HuffmanDecoder::Tree { left, right } => {
-                let bit = parser.take(1)?;
-                match bit {
-                    0 => left.decode(parser),
-                    1 => right.decode(parser),
-                    _ => unreachable!(),
-                }
-            }
-            HuffmanDecoder::Absent => panic!(),
+        let index = parser.peek_padded(self.max_bits as usize)? as usize;
+        let (symbol, width) = self.table[index];
+        if width == 0 {
+            return Err(Error::UnknownHuffmanCode);
         }
+        parser.consume(width as usize)?;
+
+        Ok(symbol)
     }
 }
 
 pub fn build_example_tree() -> HuffmanDecoder {
-    let mut tree = HuffmanDecoder::Absent;
-    assert!(tree.insert(b'A', 2));
-    assert!(tree.insert(b'C', 2));
-    assert!(tree.insert(b'B', 1));
-    tree
+    let mut widths = vec![0; 68];
+    widths[b'A' as usize] = 2;
+    widths[b'B' as usize] = 1;
+    widths[b'C' as usize] = 2;
+    HuffmanDecoder::from_number_of_bits(widths).unwrap()
 }
@@ -20,9 +20,15 @@ pub enum Error {
     CorruptedTable,
     #[error{"Corrupted file: Max code value in sequence decoding has been exceeded"}]
     SequenceCodeMaxValueExceeded,
+    #[error{"Huffman weights do not describe a valid tree"}]
+    InvalidHuffmanWeights,
+    #[error{"Huffman prefix codes overlap"}]
+    OverlappingHuffmanCodes,
+    #[error{"Bitstream does not match any Huffman prefix code"}]
+    UnknownHuffmanCode,
 }
 
-pub type Result<T> = eyre::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
 /// A (possibly) stateful bit-level decoder
 pub trait BitDecoder<Out = u8> {
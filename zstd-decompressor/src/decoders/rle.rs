@@ -2,7 +2,7 @@ use crate::parsing::BackwardBitParser;
 
 use super::{BitDecoder, Result};
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct RLEDecoder {
     byte: u8,
 }
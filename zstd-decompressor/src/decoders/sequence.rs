@@ -1,21 +1,70 @@
 use crate::parsing::BackwardBitParser;
 
-use super::{BitDecoder, Result};
+use super::{fse::FseDecoder, rle::RLEDecoder, BitDecoder, Result};
+
+/// Decoder of the literals lengths, offsets or match lengths codes, dispatched
+/// without trait objects
+#[derive(Clone)]
+pub enum CodeDecoder<'t> {
+    Rle(RLEDecoder),
+    Fse(FseDecoder<'t>),
+}
+
+impl BitDecoder<u16> for CodeDecoder<'_> {
+    #[inline]
+    fn initialize(&mut self, bitstream: &mut BackwardBitParser) -> Result<()> {
+        match self {
+            CodeDecoder::Rle(decoder) => decoder.initialize(bitstream),
+            CodeDecoder::Fse(decoder) => decoder.initialize(bitstream),
+        }
+    }
+
+    #[inline]
+    fn expected_bits(&self) -> usize {
+        match self {
+            CodeDecoder::Rle(decoder) => decoder.expected_bits(),
+            CodeDecoder::Fse(decoder) => decoder.expected_bits(),
+        }
+    }
+
+    #[inline]
+    fn symbol(&mut self) -> u16 {
+        match self {
+            CodeDecoder::Rle(decoder) => decoder.symbol(),
+            CodeDecoder::Fse(decoder) => decoder.symbol(),
+        }
+    }
+
+    #[inline]
+    fn update_bits(&mut self, bitstream: &mut BackwardBitParser) -> Result<bool> {
+        match self {
+            CodeDecoder::Rle(decoder) => decoder.update_bits(bitstream),
+            CodeDecoder::Fse(decoder) => decoder.update_bits(bitstream),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            CodeDecoder::Rle(decoder) => decoder.reset(),
+            CodeDecoder::Fse(decoder) => decoder.reset(),
+        }
+    }
+}
 
-pub struct SequenceDecoder<'d> {
-    ll_code_decoder: &'d mut dyn BitDecoder<u16>,
-    cmov_code_decoder: &'d mut dyn BitDecoder<u16>,
-    ml_code_decoder: &'d mut dyn BitDecoder<u16>,
+pub struct SequenceDecoder<'t> {
+    ll_code_decoder: CodeDecoder<'t>,
+    cmov_code_decoder: CodeDecoder<'t>,
+    ml_code_decoder: CodeDecoder<'t>,
     ll_value: usize,
     cmov_value: usize,
     match_value: usize,
 }
 
-impl<'d> SequenceDecoder<'d> {
+impl<'t> SequenceDecoder<'t> {
     pub fn new(
-        ll_code_decoder: &'d mut dyn BitDecoder<u16>,
-        cmov_code_decoder: &'d mut dyn BitDecoder<u16>,
-        ml_code_decoder: &'d mut dyn BitDecoder<u16>,
+        ll_code_decoder: CodeDecoder<'t>,
+        cmov_code_decoder: CodeDecoder<'t>,
+        ml_code_decoder: CodeDecoder<'t>,
     ) -> Self {
         SequenceDecoder {
             ll_code_decoder,
@@ -27,15 +76,17 @@ impl<'d> SequenceDecoder<'d> {
         }
     }
 
-    pub fn get_value(
-        &self,
+    /// Value of `code` given its baseline and number of extra bits in `table`. `code`
+    /// must be a valid index of `table`.
+    #[inline]
+    fn get_value(
         code: u16,
-        table: &[(u16, usize, usize)],
+        table: &[(usize, usize)],
         bitstream: &mut BackwardBitParser,
     ) -> Result<usize> {
-        let (_, baseline, nb_bits) = table.iter().find(|v| v.0 == code).unwrap();
+        let (baseline, nb_bits) = table[code as usize];
 
-        Ok(bitstream.take(*nb_bits)? as usize + *baseline)
+        Ok(bitstream.take(nb_bits)? as usize + baseline)
     }
 
     pub fn update_symbol_value(&mut self, bitstream: &mut BackwardBitParser) -> Result<bool> {
@@ -48,14 +99,14 @@ impl<'d> SequenceDecoder<'d> {
         }
 
         self.cmov_value = (1usize << offset_code) + bitstream.take(offset_code as usize)? as usize;
-        self.match_value = self.get_value(match_l_code, &ML_CODE_TO_VALUE, bitstream)?;
-        self.ll_value = self.get_value(ll_code, &LL_CODE_TO_VALUE, bitstream)?;
+        self.match_value = Self::get_value(match_l_code, &ML_CODE_TO_VALUE, bitstream)?;
+        self.ll_value = Self::get_value(ll_code, &LL_CODE_TO_VALUE, bitstream)?;
 
         Ok(false)
     }
 }
 
-impl<'a> BitDecoder<(usize, usize, usize)> for SequenceDecoder<'a> {
+impl BitDecoder<(usize, usize, usize)> for SequenceDecoder<'_> {
     fn initialize(&mut self, bitstream: &mut BackwardBitParser) -> Result<()> {
         self.ll_code_decoder.initialize(bitstream)?;
         self.cmov_code_decoder.initialize(bitstream)?;
@@ -88,104 +139,111 @@ impl<'a> BitDecoder<(usize, usize, usize)> for SequenceDecoder<'a> {
     }
 
     fn reset(&mut self) {
-        todo!()
+        self.ll_code_decoder.reset();
+        self.cmov_code_decoder.reset();
+        self.ml_code_decoder.reset();
+        self.ll_value = 0;
+        self.cmov_value = 0;
+        self.match_value = 0;
     }
 }
 
 const MAX_OFFSET_CODE: u16 = 31;
 const MAX_LL_CODE: u16 = 35;
 const MAX_ML_CODE: u16 = 52;
-const ML_CODE_TO_VALUE: [(u16, usize, usize); 53] = [
-    (0, 3, 0),
-    (1, 4, 0),
-    (2, 5, 0),
-    (3, 6, 0),
-    (4, 7, 0),
-    (5, 8, 0),
-    (6, 9, 0),
-    (7, 10, 0),
-    (8, 11, 0),
-    (9, 12, 0),
-    (10, 13, 0),
-    (11, 14, 0),
-    (12, 15, 0),
-    (13, 16, 0),
-    (14, 17, 0),
-    (15, 18, 0),
-    (16, 19, 0),
-    (17, 20, 0),
-    (18, 21, 0),
-    (19, 22, 0),
-    (20, 23, 0),
-    (21, 24, 0),
-    (22, 25, 0),
-    (23, 26, 0),
-    (24, 27, 0),
-    (25, 28, 0),
-    (26, 29, 0),
-    (27, 30, 0),
-    (28, 31, 0),
-    (29, 32, 0),
-    (30, 33, 0),
-    (31, 34, 0),
-    (32, 35, 1),
-    (33, 37, 1),
-    (34, 39, 1),
-    (35, 41, 1),
-    (36, 43, 2),
-    (37, 47, 2),
-    (38, 51, 3),
-    (39, 59, 3),
-    (40, 67, 4),
-    (41, 83, 4),
-    (42, 99, 5),
-    (43, 131, 7),
-    (44, 259, 8),
-    (45, 515, 9),
-    (46, 1027, 10),
-    (47, 2051, 11),
-    (48, 4099, 12),
-    (49, 8195, 13),
-    (50, 16387, 14),
-    (51, 32771, 15),
-    (52, 65539, 16),
+/// Baseline and number of extra bits of each match length code
+const ML_CODE_TO_VALUE: [(usize, usize); 53] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 0),
+    (12, 0),
+    (13, 0),
+    (14, 0),
+    (15, 0),
+    (16, 0),
+    (17, 0),
+    (18, 0),
+    (19, 0),
+    (20, 0),
+    (21, 0),
+    (22, 0),
+    (23, 0),
+    (24, 0),
+    (25, 0),
+    (26, 0),
+    (27, 0),
+    (28, 0),
+    (29, 0),
+    (30, 0),
+    (31, 0),
+    (32, 0),
+    (33, 0),
+    (34, 0),
+    (35, 1),
+    (37, 1),
+    (39, 1),
+    (41, 1),
+    (43, 2),
+    (47, 2),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 5),
+    (131, 7),
+    (259, 8),
+    (515, 9),
+    (1027, 10),
+    (2051, 11),
+    (4099, 12),
+    (8195, 13),
+    (16387, 14),
+    (32771, 15),
+    (65539, 16),
 ];
 
-const LL_CODE_TO_VALUE: [(u16, usize, usize); 36] = [
-    (0, 0, 0),
-    (1, 1, 0),
-    (2, 2, 0),
-    (3, 3, 0),
-    (4, 4, 0),
-    (5, 5, 0),
-    (6, 6, 0),
-    (7, 7, 0),
-    (8, 8, 0),
-    (9, 9, 0),
-    (10, 10, 0),
-    (11, 11, 0),
-    (12, 12, 0),
-    (13, 13, 0),
-    (14, 14, 0),
-    (15, 15, 0),
-    (16, 16, 1),
-    (17, 18, 1),
-    (18, 20, 1),
-    (19, 22, 1),
-    (20, 24, 2),
-    (21, 28, 2),
-    (22, 32, 3),
-    (23, 40, 3),
-    (24, 48, 4),
-    (25, 64, 6),
-    (26, 128, 7),
-    (27, 256, 8),
-    (28, 512, 9),
-    (29, 1024, 10),
-    (30, 2048, 11),
-    (31, 4096, 12),
-    (32, 8192, 13),
-    (33, 16384, 14),
-    (34, 32768, 15),
-    (35, 65536, 16),
+/// Baseline and number of extra bits of each literals length code
+const LL_CODE_TO_VALUE: [(usize, usize); 36] = [
+    (0, 0),
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 0),
+    (12, 0),
+    (13, 0),
+    (14, 0),
+    (15, 0),
+    (16, 1),
+    (18, 1),
+    (20, 1),
+    (22, 1),
+    (24, 2),
+    (28, 2),
+    (32, 3),
+    (40, 3),
+    (48, 4),
+    (64, 6),
+    (128, 7),
+    (256, 8),
+    (512, 9),
+    (1024, 10),
+    (2048, 11),
+    (4096, 12),
+    (8192, 13),
+    (16384, 14),
+    (32768, 15),
+    (65536, 16),
 ];
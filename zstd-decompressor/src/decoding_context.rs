@@ -1,7 +1,14 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
 use twox_hash::XxHash64;
 
+#[cfg(feature = "std")]
+use crate::timings::Timings;
 use crate::{
-    decoders::huffman::HuffmanDecoder, frame::MAX_WIN_SIZE, sequences::SymbolCompressionMode,
+    block::MAX_BLOCK_SIZE, decoders::huffman::HuffmanDecoder, dictionary::Dictionary,
+    frame::MAX_WIN_SIZE, output::Output, sequences::SymbolCompressionMode, window::Window,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -12,38 +19,190 @@ pub enum Error {
     NullOffsetError,
     #[error{"Error while decoding sequence : impossible value decoded"}]
     ImpossibleValue,
+    #[error{"Sequences decode to more than {0} bytes"}]
+    BlockOutputTooBig(usize),
+    #[error{"Destination buffer is too small: {requested} bytes requested out of {available} available"}]
+    DstTooSmall { requested: usize, available: usize },
 }
 
-pub struct DecodingContext {
+/// Repeat offsets at the start of a frame
+pub const DEFAULT_OFFSETS: [usize; 3] = [1, 4, 8];
+
+pub struct DecodingContext<'a> {
     pub huffman_decoder: Option<HuffmanDecoder>,
-    pub decoded: Vec<u8>,
+    /// Decoded output which has not been flushed yet
+    pub decoded: Output<'a>,
+    /// Last `window_size` bytes of flushed output, used to resolve match offsets
+    /// reaching before `decoded`
+    pub history: Window,
+    /// Number of bytes decoded since the start of the frame
+    pub produced: u64,
+    /// Content size announced by the frame header, if any
+    pub content_size: Option<u64>,
     pub offsets: [usize; 3],
     pub window_size: u64,
     pub ll_repeat_decoder: Option<SymbolCompressionMode>,
     pub cmov_repeat_decoder: Option<SymbolCompressionMode>,
     pub ml_repeat_decoder: Option<SymbolCompressionMode>,
     pub checksum: Option<XxHash64>,
+    /// Literals of the block being decoded, kept to reuse the allocation
+    pub literals: Vec<u8>,
+    /// Time spent in each phase of the decoding, only measured when set. They are
+    /// accumulated across frames.
+    #[cfg(feature = "std")]
+    pub timings: Option<Timings>,
+}
+
+/// Context with an empty window, to be `reset` for a frame
+impl Default for DecodingContext<'_> {
+    fn default() -> Self {
+        DecodingContext {
+            huffman_decoder: None,
+            decoded: Output::default(),
+            history: Window::new(0),
+            produced: 0,
+            content_size: None,
+            offsets: DEFAULT_OFFSETS,
+            window_size: 0,
+            ll_repeat_decoder: None,
+            cmov_repeat_decoder: None,
+            ml_repeat_decoder: None,
+            checksum: None,
+            literals: Vec::new(),
+            #[cfg(feature = "std")]
+            timings: None,
+        }
+    }
 }
 
-impl DecodingContext {
+impl<'a> DecodingContext<'a> {
     pub fn new(window_size: u64) -> Result<Self, Error> {
-        if window_size > MAX_WIN_SIZE {
+        Self::with_max_window_size(window_size, MAX_WIN_SIZE)
+    }
+
+    /// Create a context, failing if `window_size` is larger than `max_window_size`
+    pub fn with_max_window_size(window_size: u64, max_window_size: u64) -> Result<Self, Error> {
+        let mut context = Self::default();
+        context.reset(window_size, max_window_size)?;
+
+        Ok(context)
+    }
+
+    /// Bring the context back to the state of a new context for a frame with a window of
+    /// `window_size`, keeping its allocations. Fail if `window_size` is larger than
+    /// `max_window_size`.
+    pub fn reset(&mut self, window_size: u64, max_window_size: u64) -> Result<(), Error> {
+        if window_size > max_window_size {
             return Err(Error::WindowSizeTooBig {
-                max: MAX_WIN_SIZE,
+                max: max_window_size,
                 got: window_size,
             });
         }
 
-        Ok(DecodingContext {
-            huffman_decoder: None,
-            decoded: Vec::new(),
-            offsets: [1, 4, 8],
-            window_size,
-            ll_repeat_decoder: None,
-            cmov_repeat_decoder: None,
-            ml_repeat_decoder: None,
-            checksum: None,
-        })
+        self.huffman_decoder = None;
+        self.decoded.clear();
+        self.history.reset(window_size as usize);
+        self.produced = 0;
+        self.content_size = None;
+        self.offsets = DEFAULT_OFFSETS;
+        self.window_size = window_size;
+        self.ll_repeat_decoder = None;
+        self.cmov_repeat_decoder = None;
+        self.ml_repeat_decoder = None;
+        self.checksum = None;
+
+        Ok(())
+    }
+
+    /// Create a context whose history is pre-filled with `prefix`, on top of the window.
+    /// Match offsets can reach back into the prefix.
+    pub fn with_prefix(window_size: u64, prefix: &[u8]) -> Result<Self, Error> {
+        let mut context = Self::new(window_size)?;
+        context.load_prefix(prefix);
+
+        Ok(context)
+    }
+
+    /// Create a context starting from the entropy tables, repeat offsets and content
+    /// of `dictionary`.
+    pub fn with_dictionary(window_size: u64, dictionary: &Dictionary) -> Result<Self, Error> {
+        let mut context = Self::new(window_size)?;
+        context.load_dictionary(dictionary);
+
+        Ok(context)
+    }
+
+    /// Replace the history with `prefix`, kept on top of the window
+    pub fn load_prefix(&mut self, prefix: &[u8]) {
+        self.history.reset(self.window_size as usize + prefix.len());
+        self.history.push(prefix);
+    }
+
+    /// Load the entropy tables, repeat offsets and content of `dictionary`
+    pub fn load_dictionary(&mut self, dictionary: &Dictionary) {
+        self.load_prefix(&dictionary.content);
+
+        if let Some(entropy) = &dictionary.entropy {
+            self.huffman_decoder = Some(entropy.huffman_decoder.clone());
+            self.ll_repeat_decoder = Some(SymbolCompressionMode::FseCompressedMode(
+                entropy.literals_lengths_table.clone(),
+            ));
+            self.cmov_repeat_decoder = Some(SymbolCompressionMode::FseCompressedMode(
+                entropy.offsets_table.clone(),
+            ));
+            self.ml_repeat_decoder = Some(SymbolCompressionMode::FseCompressedMode(
+                entropy.match_lengths_table.clone(),
+            ));
+        }
+        self.offsets = dictionary.offsets;
+    }
+
+    /// Move the state of the context to a context writing to `output`, keeping its
+    /// allocations. Return the new context and the output of this one.
+    pub fn with_output<'b>(self, output: Output<'b>) -> (DecodingContext<'b>, Output<'a>) {
+        let context = DecodingContext {
+            huffman_decoder: self.huffman_decoder,
+            decoded: output,
+            history: self.history,
+            produced: self.produced,
+            content_size: self.content_size,
+            offsets: self.offsets,
+            window_size: self.window_size,
+            ll_repeat_decoder: self.ll_repeat_decoder,
+            cmov_repeat_decoder: self.cmov_repeat_decoder,
+            ml_repeat_decoder: self.ml_repeat_decoder,
+            checksum: self.checksum,
+            literals: self.literals,
+            #[cfg(feature = "std")]
+            timings: self.timings,
+        };
+
+        (context, self.decoded)
+    }
+
+    /// Maximum decoded size of a block, the smallest of the window size and 128KiB
+    pub fn block_maximum_size(&self) -> usize {
+        self.window_size.min(MAX_BLOCK_SIZE as u64) as usize
+    }
+
+    /// Maximum decoded size of the next block, bounded by the rest of the content size if
+    /// it is known
+    pub fn next_block_maximum_size(&self) -> usize {
+        let max = self.block_maximum_size();
+        match self.content_size {
+            Some(size) => size.saturating_sub(self.produced).min(max as u64) as usize,
+            None => max,
+        }
+    }
+
+    /// Write the decoded output to `sink` and move it to the history
+    #[cfg(feature = "std")]
+    pub fn flush<W: Write>(&mut self, sink: &mut W) -> io::Result<()> {
+        sink.write_all(&self.decoded)?;
+        self.history.push(&self.decoded);
+        self.decoded.clear();
+
+        Ok(())
     }
 
     /// Decode an offset and properly maintain the three repeat offsets
@@ -51,9 +210,13 @@ impl DecodingContext {
         match (offset, literals_length) {
             (0, _) => return Err(Error::NullOffsetError),
             (3, 0) => {
+                let offset = self.offsets[0] - 1;
+                if offset == 0 {
+                    return Err(Error::NullOffsetError);
+                }
                 self.offsets[2] = self.offsets[1];
                 self.offsets[1] = self.offsets[0];
-                self.offsets[0] -= 1;
+                self.offsets[0] = offset;
             }
             (3, _) | (2, 0) => {
                 let temp = self.offsets[2];
@@ -77,46 +240,124 @@ impl DecodingContext {
     /// Execute the sequences while updating the offsets
     pub fn execute_sequences(
         &mut self,
-        sequences: Vec<(usize, usize, usize)>,
+        sequences: &[(usize, usize, usize)],
         mut literals: &[u8],
     ) -> Result<(), Error> {
-        for (literal_length, decoded_offset, match_length) in sequences {
-            let decoded_offset = self.decode_offset(decoded_offset, literal_length)?;
+        let start = self.decoded.len();
 
-            if literal_length > literals.len()
-                || decoded_offset > self.decoded.len() + literal_length
-            {
-                return Err(Error::ImpossibleValue);
-            }
+        for &(literal_length, offset, match_length) in sequences {
+            self.execute_sequence(start, literal_length, offset, match_length, &mut literals)?;
+        }
+        self.decoded.check_space(literals.len())?;
+        self.decoded.extend_from_slice(literals);
+
+        Ok(())
+    }
 
-            self.decoded.extend_from_slice(&literals[..literal_length]);
-            literals = &literals[literal_length..];
+    /// Execute one sequence of the block starting at `block_start` in `decoded`, taking
+    /// its literals from the front of `literals`, and update the offsets
+    #[inline]
+    pub fn execute_sequence(
+        &mut self,
+        block_start: usize,
+        literal_length: usize,
+        offset: usize,
+        match_length: usize,
+        literals: &mut &[u8],
+    ) -> Result<(), Error> {
+        let decoded_offset = self.decode_offset(offset, literal_length)?;
 
-            for _ in 0..match_length {
-                self.decoded
-                    .push(self.decoded[self.decoded.len() - decoded_offset]);
-            }
+        // Corrupted sequences could otherwise expand to gigabytes
+        let max = self.block_maximum_size();
+        if self.decoded.len() - block_start + literal_length + match_length > max {
+            return Err(Error::BlockOutputTooBig(max));
         }
 
-        for literal in literals {
-            self.decoded.push(*literal);
+        if literal_length > literals.len()
+            || decoded_offset > self.decoded.len() + literal_length + self.history.len()
+        {
+            return Err(Error::ImpossibleValue);
         }
+        self.decoded.check_space(literal_length + match_length)?;
+
+        let (copied, rest) = literals.split_at(literal_length);
+        self.decoded.extend_from_slice(copied);
+        *literals = rest;
+
+        // Part of the match located in the flushed history
+        let mut match_length = match_length;
+        if decoded_offset > self.decoded.len() {
+            let distance = decoded_offset - self.decoded.len();
+            match_length -= self
+                .history
+                .copy_to(distance, match_length, &mut self.decoded);
+        }
+
+        copy_match(&mut self.decoded, decoded_offset, match_length);
 
         Ok(())
     }
 }
 
+/// Append `len` bytes starting `offset` bytes before the end of `output`. When the match
+/// overlaps its own output, it repeats with a period of `offset`, so each copy can be
+/// twice as large as the previous one.
+#[inline]
+fn copy_match(output: &mut Output, offset: usize, len: usize) {
+    if len == 0 {
+        return;
+    }
+
+    let start = output.len() - offset;
+    let mut remaining = len;
+
+    while remaining > 0 {
+        let chunk = remaining.min(output.len() - start);
+        output.extend_from_within(start..start + chunk);
+        remaining -= chunk;
+    }
+}
+
 #[test]
 fn execute_sequences() {
     let mut context = DecodingContext::new(0x42).unwrap();
     context
         .execute_sequences(
-            vec![(3, 5, 3), (2, 11, 1)],
+            &[(3, 5, 3), (2, 11, 1)],
             &[0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68],
         )
         .unwrap();
     assert_eq!(
-        vec![0x61, 0x62, 0x63, 0x62, 0x63, 0x62, 0x64, 0x65, 0x61, 0x66, 0x67, 0x68],
-        context.decoded
+        [0x61, 0x62, 0x63, 0x62, 0x63, 0x62, 0x64, 0x65, 0x61, 0x66, 0x67, 0x68],
+        context.decoded[..]
     );
 }
+
+#[test]
+#[cfg(feature = "std")]
+fn execute_sequences_from_history() {
+    let mut context = DecodingContext::new(0x42).unwrap();
+    context.decoded.extend_from_slice(&[0x61, 0x62, 0x63]);
+    context.flush(&mut io::sink()).unwrap();
+    context
+        .execute_sequences(&[(1, 7, 4)], &[0x64, 0x65])
+        .unwrap();
+    assert_eq!([0x64, 0x61, 0x62, 0x63, 0x64, 0x65], context.decoded[..]);
+}
+
+#[test]
+fn execute_sequences_from_prefix() {
+    let mut context = DecodingContext::with_prefix(0x42, &[0x61, 0x62, 0x63]).unwrap();
+    context.execute_sequences(&[(0, 6, 2)], &[0x64]).unwrap();
+    assert_eq!([0x61, 0x62, 0x64], context.decoded[..]);
+    assert!(context.execute_sequences(&[(0, 10, 1)], &[]).is_err());
+}
+
+#[test]
+fn execute_sequences_overlapping_matches() {
+    let mut context = DecodingContext::new(0x42).unwrap();
+    context
+        .execute_sequences(&[(1, 4, 9), (2, 6, 7)], &[0x61, 0x62, 0x63])
+        .unwrap();
+    assert_eq!(b"aaaaaaaaaabcabcabca", &context.decoded[..]);
+}
@@ -0,0 +1,127 @@
+use crate::{
+    decoding_context,
+    decoding_context::DecodingContext,
+    dictionary::Dictionary,
+    frame::{ChecksumPolicy, Frame},
+    options::DecoderOptions,
+    parsing::ForwardByteParser,
+    Error,
+};
+use alloc::vec::Vec;
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// Decompression state reusable across frames and calls, comparable to `ZSTD_DCtx`.
+///
+/// The history, output and literals buffers of the decoding context are kept between
+/// frames, so decompressing many small inputs with the same `Decompressor` does not
+/// allocate them again. A `Decompressor` is meant to be kept per thread.
+pub struct Decompressor {
+    context: DecodingContext<'static>,
+    options: DecoderOptions,
+    checksum_policy: ChecksumPolicy,
+    dictionary: Option<Dictionary>,
+}
+
+impl Default for Decompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decompressor {
+    pub fn new() -> Self {
+        Decompressor {
+            context: DecodingContext::default(),
+            options: DecoderOptions::default(),
+            checksum_policy: ChecksumPolicy::default(),
+            dictionary: None,
+        }
+    }
+
+    /// Set the limits of the decoder
+    pub fn with_options(mut self, options: DecoderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set what to do when the content checksum of a frame does not match
+    pub fn with_checksum_policy(mut self, policy: ChecksumPolicy) -> Self {
+        self.checksum_policy = policy;
+        self
+    }
+
+    /// Decode frames starting from `dictionary`
+    pub fn with_dictionary(mut self, dictionary: Dictionary) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    /// Decompress all the frames of `input`
+    pub fn decompress(&mut self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        self.decompress_to_vec(input, &mut output)?;
+
+        Ok(output)
+    }
+
+    /// Decompress all the frames of `input`, appending their content to `output`.
+    /// Return the number of bytes appended.
+    pub fn decompress_to_vec(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<usize> {
+        let start = output.len();
+
+        let mut frames = ForwardByteParser::new(input).iter_with_options(self.options);
+        while let Some(frame) = frames.next() {
+            let index = frames.frames - 1;
+            match frame? {
+                Frame::ZStandardFrame(frame) => frame
+                    .with_checksum_policy(self.checksum_policy)
+                    .decode_with_context(&mut self.context, self.dictionary.as_ref(), output)
+                    .map_err(|e| e.in_frame(index).relative_to(input, 0))?,
+                Frame::SkippableFrame(skippable) => output.extend_from_slice(skippable.data),
+            }
+            self.options.check_output((output.len() - start) as u64)?;
+        }
+
+        Ok(output.len() - start)
+    }
+
+    /// Decompress all the frames of `input` into `dst`, without allocating the output.
+    /// Fail with `ErrorKind::DstTooSmall` if the content does not fit in `dst`. Return the
+    /// number of bytes written.
+    pub fn decompress_into(&mut self, input: &[u8], dst: &mut [u8]) -> Result<usize> {
+        let mut written = 0;
+
+        let mut frames = ForwardByteParser::new(input).iter_with_options(self.options);
+        while let Some(frame) = frames.next() {
+            let index = frames.frames - 1;
+            let remaining = &mut dst[written..];
+            written += match frame? {
+                Frame::ZStandardFrame(frame) => frame
+                    .with_checksum_policy(self.checksum_policy)
+                    .decode_into(&mut self.context, self.dictionary.as_ref(), remaining)
+                    .map_err(|e| e.in_frame(index).relative_to(input, 0))?,
+                Frame::SkippableFrame(skippable) => {
+                    let len = skippable.data.len();
+                    let available = remaining.len();
+                    remaining
+                        .get_mut(..len)
+                        .ok_or_else(|| {
+                            Error::from(decoding_context::Error::DstTooSmall {
+                                requested: len,
+                                available,
+                            })
+                            .in_frame(index)
+                            .at(skippable.data)
+                            .relative_to(input, 0)
+                        })?
+                        .copy_from_slice(skippable.data);
+                    len
+                }
+            };
+            self.options.check_output(written as u64)?;
+        }
+
+        Ok(written)
+    }
+}
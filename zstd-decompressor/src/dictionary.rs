@@ -0,0 +1,125 @@
+use crate::{
+    decoders::{fse::FseTable, huffman::HuffmanDecoder},
+    decoding_context::DEFAULT_OFFSETS,
+    parsing::{ForwardBitParser, ForwardByteParser},
+    Section,
+};
+use alloc::vec::Vec;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error{"Unrecognised dictionary magic: {0:#x}"}]
+    UnrecognizedMagic(u32),
+    #[error{"Repeat offset {0} is null or larger than the dictionary content"}]
+    InvalidRepeatOffset(usize),
+}
+
+type Result<T> = core::result::Result<T, crate::Error>;
+
+pub(crate) const MAGIC_DICTIONARY: u32 = 0xEC30A437;
+
+/// Entropy tables of a formatted dictionary
+#[derive(Debug, Clone)]
+pub struct EntropyTables {
+    pub huffman_decoder: HuffmanDecoder,
+    pub offsets_table: FseTable,
+    pub match_lengths_table: FseTable,
+    pub literals_lengths_table: FseTable,
+}
+
+/// Dictionary used to decode frames, either a formatted dictionary (see
+/// https://datatracker.ietf.org/doc/html/rfc8878#name-dictionary-format) or raw content
+/// acting as a prefix of the frames
+#[derive(Debug, Clone)]
+pub struct Dictionary {
+    /// Dictionary ID, always 0 for raw content
+    pub id: u32,
+    /// Entropy tables, absent for raw content
+    pub entropy: Option<EntropyTables>,
+    pub offsets: [usize; 3],
+    pub content: Vec<u8>,
+}
+
+impl Dictionary {
+    /// Parse a formatted dictionary. Errors are located relatively to the start of
+    /// `data`.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        Self::parse_fields(data).map_err(|e| e.at(data).relative_to(data, 0))
+    }
+
+    fn parse_fields(data: &[u8]) -> Result<Self> {
+        let mut input = ForwardByteParser::new(data);
+
+        let magic = input.le_u32()?;
+        if magic != MAGIC_DICTIONARY {
+            return Err(Error::UnrecognizedMagic(magic).into());
+        }
+        let id = input.le_u32()?;
+
+        let start = input.remaining();
+        let huffman_decoder = HuffmanDecoder::parse(&mut input).map_err(|e| {
+            crate::Error::from(e)
+                .in_section(Section::HuffmanTree)
+                .at(start)
+        })?;
+        let entropy = EntropyTables {
+            huffman_decoder,
+            offsets_table: Self::parse_fse_table(&mut input)?,
+            match_lengths_table: Self::parse_fse_table(&mut input)?,
+            literals_lengths_table: Self::parse_fse_table(&mut input)?,
+        };
+
+        let start = input.remaining();
+        let offsets = [
+            input.le_u32()? as usize,
+            input.le_u32()? as usize,
+            input.le_u32()? as usize,
+        ];
+
+        let content = input.remaining().to_vec();
+
+        if let Some(&offset) = offsets
+            .iter()
+            .find(|&&offset| offset == 0 || offset > content.len())
+        {
+            return Err(crate::Error::from(Error::InvalidRepeatOffset(offset)).at(start));
+        }
+
+        Ok(Dictionary {
+            id,
+            entropy: Some(entropy),
+            offsets,
+            content,
+        })
+    }
+
+    /// Use `content` as a raw content dictionary: it only pre-fills the history
+    pub fn raw(content: Vec<u8>) -> Self {
+        Dictionary {
+            id: 0,
+            entropy: None,
+            offsets: DEFAULT_OFFSETS,
+            content,
+        }
+    }
+
+    /// Parse `data` as a formatted dictionary if it starts with the dictionary magic
+    /// number, or use it as raw content otherwise
+    pub fn load(data: Vec<u8>) -> Result<Self> {
+        if data.starts_with(&MAGIC_DICTIONARY.to_le_bytes()) {
+            Self::parse(&data)
+        } else {
+            Ok(Self::raw(data))
+        }
+    }
+
+    fn parse_fse_table(input: &mut ForwardByteParser) -> Result<FseTable> {
+        let data = input.remaining();
+        let locate = |e: crate::Error| e.in_section(Section::FseTable).at(data);
+        let mut parser = ForwardBitParser::new(data).map_err(|e| locate(e.into()))?;
+        let table = FseTable::parse(&mut parser).map_err(|e| locate(e.into()))?;
+        *input = ForwardByteParser::new(&data[parser.bytes_read()..]);
+
+        Ok(table)
+    }
+}
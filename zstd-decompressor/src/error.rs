@@ -0,0 +1,341 @@
+use alloc::{boxed::Box, format, string::ToString, vec};
+use core::{error::Error as StdError, fmt};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{block, decoders, decoding_context, dictionary, frame, literals, parsing, sequences};
+#[cfg(feature = "std")]
+use crate::{parallel, stream};
+
+/// Part of a frame in which an error happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    FrameHeader,
+    BlockHeader,
+    LiteralsHeader,
+    HuffmanTree,
+    /// Huffman-coded streams of the literals
+    HuffmanStreams,
+    SequencesHeader,
+    FseTable,
+    /// Bitstream of the sequences, and the execution of the decoded sequences
+    SequenceBitstream,
+    Checksum,
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Section::FrameHeader => "frame header",
+            Section::BlockHeader => "block header",
+            Section::LiteralsHeader => "literals header",
+            Section::HuffmanTree => "Huffman tree",
+            Section::HuffmanStreams => "Huffman streams",
+            Section::SequencesHeader => "sequences header",
+            Section::FseTable => "FSE table",
+            Section::SequenceBitstream => "sequence bitstream",
+            Section::Checksum => "checksum",
+        })
+    }
+}
+
+/// Category of an error, independent of where it happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The input or a bitstream ends too early
+    UnexpectedEnd,
+    UnrecognizedMagic,
+    /// A reserved field or value is used
+    ReservedValue,
+    /// Invalid content, e.g. a table, a bitstream or an offset which cannot be decoded
+    Corrupted,
+    WindowTooBig,
+    BlockTooBig,
+    /// The decoded content does not match the content size of the frame header
+    ContentSizeMismatch,
+    ChecksumMismatch,
+    MissingDictionary,
+    DictionaryMismatch,
+    TooManyFrames,
+    TooManyBlocks,
+    OutputTooBig,
+    DstTooSmall,
+    Io,
+    /// A decoding thread panicked or stopped
+    Thread,
+}
+
+/// Error of the decoder, locating where it happened in the input.
+///
+/// The byte offset is relative to the input given to the function which returned the
+/// error, e.g. `Decompressor::decompress`, or to the frame header for the decoding
+/// functions of a parsed `ZStandard` frame. It points at the start of the section, or of the block or
+/// frame when the section is unknown. The source is the error of the module which
+/// detected it.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    source: Box<dyn StdError + Send + Sync>,
+    /// Address in memory of the location, kept to compute the offset in a larger input
+    address: Option<usize>,
+    offset: Option<u64>,
+    bit_offset: Option<u64>,
+    frame: Option<usize>,
+    block: Option<usize>,
+    section: Option<Section>,
+}
+
+impl Error {
+    fn new<E: StdError + Send + Sync + 'static>(kind: ErrorKind, source: E) -> Self {
+        Error {
+            kind,
+            source: Box::new(source),
+            address: None,
+            offset: None,
+            bit_offset: None,
+            frame: None,
+            block: None,
+            section: None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Offset in bytes of the location of the error in the input
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    /// Offset in bits inside the bitstream starting at `offset`, counted from the end of
+    /// the bitstream for the backward Huffman and sequence bitstreams
+    pub fn bit_offset(&self) -> Option<u64> {
+        self.bit_offset
+    }
+
+    /// Index of the frame in the input, skippable frames included
+    pub fn frame(&self) -> Option<usize> {
+        self.frame
+    }
+
+    /// Index of the block in its frame
+    pub fn block(&self) -> Option<usize> {
+        self.block
+    }
+
+    pub fn section(&self) -> Option<Section> {
+        self.section
+    }
+
+    /// Error of the module which detected the error, if it is an `E`, e.g. a
+    /// `frame::Error`
+    pub fn downcast_ref<E: StdError + 'static>(&self) -> Option<&E> {
+        self.source.downcast_ref()
+    }
+
+    /// Locate the error at the start of `data`, unless it is located already
+    pub(crate) fn at(mut self, data: &[u8]) -> Self {
+        self.address.get_or_insert(data.as_ptr() as usize);
+        self
+    }
+
+    /// Record that the error happened in `section`
+    pub(crate) fn in_section(mut self, section: Section) -> Self {
+        self.section.get_or_insert(section);
+        self
+    }
+
+    /// Record the position of the error inside the bitstream of the section
+    pub(crate) fn at_bit(mut self, bit_offset: u64) -> Self {
+        self.bit_offset.get_or_insert(bit_offset);
+        self
+    }
+
+    /// Record that the error happened in the block `index` of its frame
+    pub(crate) fn in_block(mut self, index: usize) -> Self {
+        self.block.get_or_insert(index);
+        self
+    }
+
+    /// Record that the error happened in the frame `index` of the input
+    pub(crate) fn in_frame(mut self, index: usize) -> Self {
+        self.frame.get_or_insert(index);
+        self
+    }
+
+    /// Compute the offset of the error in `input`, which starts at `position` in the
+    /// whole input. Parsed sections borrow the input, so the offset is the distance
+    /// between their addresses. Errors located outside of `input` are left as is.
+    pub(crate) fn relative_to(mut self, input: &[u8], position: u64) -> Self {
+        let start = input.as_ptr() as usize;
+        if let Some(address) = self.address {
+            if (start..=start + input.len()).contains(&address) {
+                self.offset = Some(position + (address - start) as u64);
+            }
+        }
+        self
+    }
+
+    /// Set the offset of the error in the input if it is unknown, for inputs which are
+    /// not in memory at once
+    #[cfg(feature = "std")]
+    pub(crate) fn at_offset(mut self, offset: u64) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)?;
+
+        let mut location = vec![];
+        if let Some(frame) = self.frame {
+            location.push(format!("frame {frame}"));
+        }
+        if let Some(block) = self.block {
+            location.push(format!("block {block}"));
+        }
+        if let Some(section) = self.section {
+            location.push(section.to_string());
+        }
+        if let Some(offset) = self.offset {
+            location.push(format!("at byte {offset}"));
+        }
+        if let Some(bit_offset) = self.bit_offset {
+            location.push(format!("bit {bit_offset}"));
+        }
+
+        if !location.is_empty() {
+            write!(f, " ({})", location.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+impl From<parsing::Error> for Error {
+    fn from(error: parsing::Error) -> Self {
+        let kind = match error {
+            parsing::Error::NotEnoughBytes { .. }
+            | parsing::Error::NotEnoughBits { .. }
+            | parsing::Error::EmptyInputData => ErrorKind::UnexpectedEnd,
+            parsing::Error::MaximumReadableBitsExceeded(_)
+            | parsing::Error::MaximumPeekableBitsExceeded(_)
+            | parsing::Error::NullByte => ErrorKind::Corrupted,
+        };
+        Error::new(kind, error)
+    }
+}
+
+impl From<decoders::Error> for Error {
+    fn from(error: decoders::Error) -> Self {
+        match error {
+            decoders::Error::ParsingError(error) => error.into(),
+            error => Error::new(ErrorKind::Corrupted, error),
+        }
+    }
+}
+
+impl From<decoding_context::Error> for Error {
+    fn from(error: decoding_context::Error) -> Self {
+        let kind = match error {
+            decoding_context::Error::WindowSizeTooBig { .. } => ErrorKind::WindowTooBig,
+            decoding_context::Error::NullOffsetError | decoding_context::Error::ImpossibleValue => {
+                ErrorKind::Corrupted
+            }
+            decoding_context::Error::BlockOutputTooBig(_) => ErrorKind::BlockTooBig,
+            decoding_context::Error::DstTooSmall { .. } => ErrorKind::DstTooSmall,
+        };
+        Error::new(kind, error)
+    }
+}
+
+impl From<literals::Error> for Error {
+    fn from(error: literals::Error) -> Self {
+        Error::new(ErrorKind::Corrupted, error)
+    }
+}
+
+impl From<sequences::Error> for Error {
+    fn from(error: sequences::Error) -> Self {
+        let kind = match error {
+            sequences::Error::ReservedSet => ErrorKind::ReservedValue,
+            sequences::Error::NoPreviousDecoder => ErrorKind::Corrupted,
+        };
+        Error::new(kind, error)
+    }
+}
+
+impl From<block::Error> for Error {
+    fn from(error: block::Error) -> Self {
+        let kind = match error {
+            block::Error::ReservedBlockType() => ErrorKind::ReservedValue,
+            block::Error::BlockSizeTooBig { .. } | block::Error::LargeBlockSize { .. } => {
+                ErrorKind::BlockTooBig
+            }
+        };
+        Error::new(kind, error)
+    }
+}
+
+impl From<dictionary::Error> for Error {
+    fn from(error: dictionary::Error) -> Self {
+        let kind = match error {
+            dictionary::Error::UnrecognizedMagic(_) => ErrorKind::UnrecognizedMagic,
+            dictionary::Error::InvalidRepeatOffset(_) => ErrorKind::Corrupted,
+        };
+        Error::new(kind, error)
+    }
+}
+
+impl From<frame::Error> for Error {
+    fn from(error: frame::Error) -> Self {
+        let kind = match error {
+            frame::Error::UnrecognizedMagic(_) => ErrorKind::UnrecognizedMagic,
+            frame::Error::ReservedSet(_) => ErrorKind::ReservedValue,
+            frame::Error::UnvalidChecksum(_) | frame::Error::BadCheksum => {
+                ErrorKind::ChecksumMismatch
+            }
+            frame::Error::MissingChecksum(_) => ErrorKind::UnexpectedEnd,
+            frame::Error::ContentSizeTooBig(_) | frame::Error::ContentSizeTooSmall { .. } => {
+                ErrorKind::ContentSizeMismatch
+            }
+            frame::Error::MissingDictionary(_) => ErrorKind::MissingDictionary,
+            frame::Error::DictionaryMismatch { .. } => ErrorKind::DictionaryMismatch,
+            frame::Error::MissingWindowSize => ErrorKind::Corrupted,
+            frame::Error::TooManyBlocks(_) => ErrorKind::TooManyBlocks,
+            frame::Error::TooManyFrames(_) => ErrorKind::TooManyFrames,
+            frame::Error::OutputTooBig(_) => ErrorKind::OutputTooBig,
+        };
+        Error::new(kind, error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<parallel::Error> for Error {
+    fn from(error: parallel::Error) -> Self {
+        Error::new(ErrorKind::Thread, error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<stream::Error> for Error {
+    fn from(error: stream::Error) -> Self {
+        let stream::Error::Failed(kind) = error;
+        Error::new(kind, error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::new(ErrorKind::Io, error)
+    }
+}
@@ -1,45 +1,53 @@
-use std::{any::type_name, hash::Hasher};
+use alloc::{string::String, string::ToString, vec, vec::Vec};
+use core::{any::type_name, hash::Hasher, mem};
+#[cfg(feature = "std")]
+use std::io::Write;
 
 use crate::{
-    block::Block,
+    block::{Block, MAX_BLOCK_SIZE},
     decoding_context::{self, DecodingContext},
+    dictionary::Dictionary,
+    options::DecoderOptions,
+    output::Output,
     parsing::{self, ForwardBitParser, ForwardByteParser},
     utils::{get_n_bits, int_from_array},
+    Section,
 };
 
-use eyre;
-use thiserror;
+use twox_hash::XxHash64;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error{"Unrecognised magic: {0}"}]
     UnrecognizedMagic(u32),
-    #[error{"Parsing error: {0}"}]
-    ParsingError(#[from] crate::parsing::Error),
     #[error{"Reserved value in {0} was set"}]
     ReservedSet(String),
     #[error{"Unvalid checksum in {0}"}]
     UnvalidChecksum(String),
-    #[error{"Block error: {0}"}]
-    BlockError(#[from] crate::block::Error),
-    #[error{"Dictionnary ID {0} is reserved but not registered"}]
-    UnregisteredReservedDictID(u64),
     #[error{"Expected checksum from header but is not present"}]
     MissingChecksum(#[source] parsing::Error),
     #[error{"Bad checksum, data was lost or modified"}]
     BadCheksum,
-    #[error{"Window size is too big: max {max} but got {got}"}]
-    WindowSizeTooBig { max: u64, got: u64 },
-    #[error{"Decoded block's size exceeded the annonced content size: "}]
-    ContentSizeTooBig(),
-    #[error{"Bad Offset value (0)"}]
-    NullOffsetError,
-    #[error{"Decoding context error: {0}"}]
-    DecodingContextError(#[from] decoding_context::Error),
+    #[error{"Decoded data exceeded the announced content size of {0} bytes"}]
+    ContentSizeTooBig(u64),
+    #[error{"Decoded data is smaller than the announced content size: expected {expected} bytes but got {got}"}]
+    ContentSizeTooSmall { expected: u64, got: u64 },
+    #[error{"Frame requires dictionary {0} but none was given"}]
+    MissingDictionary(u64),
+    #[error{"Frame requires dictionary {expected} but dictionary {got} was given"}]
+    DictionaryMismatch { expected: u64, got: u32 },
+    #[error{"Frame header has neither a window descriptor nor a content size"}]
+    MissingWindowSize,
+    #[error{"Frame has more than {0} blocks"}]
+    TooManyBlocks(usize),
+    #[error{"Input has more than {0} frames"}]
+    TooManyFrames(usize),
+    #[error{"Decoded data exceeded the maximum output size of {0} bytes"}]
+    OutputTooBig(u64),
 }
 
-const MAGIC_ZSTD: u32 = 0xFD2FB528;
-const MAGIC_SKIP: u32 = 0x184D2A50; //
+pub const MAGIC_ZSTD: u32 = 0xFD2FB528;
+pub const MAGIC_SKIP: u32 = 0x184D2A50;
 
 pub const MAX_WIN_SIZE: u64 = 8 << 20; // 8MiB
 
@@ -55,14 +63,29 @@ pub struct Skippable<'a> {
     pub data: &'a [u8],
 }
 
-type Result<T> = eyre::Result<T, Error>;
+type Result<T> = core::result::Result<T, crate::Error>;
 
 impl<'a> Frame<'a> {
     pub fn parse(input: &mut ForwardByteParser<'a>) -> Result<Self> {
+        Self::parse_with_options(input, &DecoderOptions::default())
+    }
+
+    /// Parse a frame, enforcing the window size and number of blocks limits of `options`
+    pub fn parse_with_options(
+        input: &mut ForwardByteParser<'a>,
+        options: &DecoderOptions,
+    ) -> Result<Self> {
+        let start = input.remaining();
+        Self::parse_frame(input, options).map_err(|e| e.at(start).relative_to(start, 0))
+    }
+
+    fn parse_frame(input: &mut ForwardByteParser<'a>, options: &DecoderOptions) -> Result<Self> {
         let magic = input.le_u32()?;
 
         match magic {
-            MAGIC_ZSTD => Ok(Frame::ZStandardFrame(ZStandard::parse(input)?)),
+            MAGIC_ZSTD => Ok(Frame::ZStandardFrame(ZStandard::parse_with_options(
+                input, options,
+            )?)),
             v if v ^ MAGIC_SKIP <= 0x0F => {
                 let data_len = input.le_u32()? as usize;
                 let sf = Skippable {
@@ -72,7 +95,7 @@ impl<'a> Frame<'a> {
 
                 Ok(Frame::SkippableFrame(sf))
             }
-            _ => Err(Error::UnrecognizedMagic(magic)),
+            _ => Err(Error::UnrecognizedMagic(magic).into()),
         }
     }
 
@@ -85,17 +108,38 @@ impl<'a> Frame<'a> {
 }
 
 pub struct FrameIterator<'a> {
+    /// Whole input, to compute the offset of the errors
+    pub(crate) input: &'a [u8],
     pub parser: ForwardByteParser<'a>,
+    pub options: DecoderOptions,
+    /// Number of frames parsed so far
+    pub frames: usize,
 }
 
 impl<'a> Iterator for FrameIterator<'a> {
     type Item = Result<Frame<'a>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.parser.len() {
-            0 => None,
-            _ => Some(Frame::parse(&mut self.parser)),
+        while !self.parser.is_empty() {
+            let start = self.parser.remaining();
+            let index = self.frames;
+            self.frames += 1;
+
+            let res = match self.options.max_frames.filter(|&max| self.frames > max) {
+                Some(max) => Err(Error::TooManyFrames(max).into()),
+                None => Frame::parse_with_options(&mut self.parser, &self.options),
+            };
+            match res {
+                Ok(Frame::SkippableFrame(_)) if !self.options.include_skippable => (),
+                res => {
+                    return Some(res.map_err(|e: crate::Error| {
+                        e.in_frame(index).at(start).relative_to(self.input, 0)
+                    }))
+                }
+            }
         }
+
+        None
     }
 }
 
@@ -108,7 +152,30 @@ pub struct Header {
 }
 
 impl Header {
+    /// Return the size in bytes of a frame header given its first byte, the frame
+    /// header descriptor. The magic number is not included.
+    pub fn size(descriptor: u8) -> usize {
+        let (dict_id_flag, descriptor) = get_n_bits(descriptor, 2);
+        let single_segment_flag = (descriptor >> 3) & 1;
+        let content_size_flag = descriptor >> 4;
+
+        let window_descriptor_size = 1 - single_segment_flag as usize;
+        let dict_id_size = [0, 1, 2, 4][dict_id_flag as usize];
+        let fcs_field_size = match (content_size_flag, single_segment_flag) {
+            (0, 0) => 0,
+            (0, _) => 1,
+            (v, _) => 1 << v,
+        };
+
+        1 + window_descriptor_size + dict_id_size + fcs_field_size
+    }
+
     pub fn parse(input: &mut ForwardByteParser<'_>) -> Result<Self> {
+        let start = input.remaining();
+        Self::parse_fields(input).map_err(|e| e.in_section(Section::FrameHeader).at(start))
+    }
+
+    fn parse_fields(input: &mut ForwardByteParser<'_>) -> Result<Self> {
         let mut header = ForwardBitParser::new(input.slice(1)?).unwrap();
 
         let dict_id_flag = header.take(2).unwrap();
@@ -116,7 +183,7 @@ impl Header {
 
         let reserved = header.take(1).unwrap();
         if reserved != 0 {
-            return Err(Error::ReservedSet(type_name::<Self>().to_string()));
+            return Err(Error::ReservedSet(type_name::<Self>().to_string()).into());
             // See https://datatracker.ietf.org/doc/html/rfc8878#name-frame-header
         }
 
@@ -146,11 +213,8 @@ impl Header {
         } else {
             None
         };
-        // if let Some(v) = dict_id {
-        //     if v <= 32767 || v >= (1 << 31) {
-        //         return Err(Error::UnregisteredReservedDictID(v)); // TODO: check registration of the dict ID
-        //     }
-        // }
+        // Reserved dictionary IDs are not rejected: they are only reserved for public
+        // registration and may be used by private dictionaries.
 
         let content_size = match fcs_field_size {
             None => None,
@@ -166,7 +230,7 @@ impl Header {
 
         let window_size = window_size
             .or(content_size)
-            .expect("Invalid configuration, no window size and no content_size !");
+            .ok_or(Error::MissingWindowSize)?;
 
         Ok(Header {
             content_checksum_flag: content_checksum_flag != 0,
@@ -176,6 +240,76 @@ impl Header {
         })
     }
 
+    /// Maximum size of the blocks of the frame, the smallest of the window size and 128KiB,
+    /// see https://datatracker.ietf.org/doc/html/rfc8878#name-block_maximum_size
+    pub fn block_maximum_size(&self) -> usize {
+        self.window_size.min(MAX_BLOCK_SIZE as u64) as usize
+    }
+
+    /// Check that `decoded` bytes do not exceed the frame content size if it is known,
+    /// and that they match it exactly once the frame is `finished`
+    pub fn check_content_size(&self, decoded: u64, finished: bool) -> Result<()> {
+        match self.content_size {
+            Some(expected) if decoded > expected => Err(Error::ContentSizeTooBig(expected).into()),
+            Some(expected) if finished && decoded < expected => Err(Error::ContentSizeTooSmall {
+                expected,
+                got: decoded,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Create the decoding context of the frame, starting from `dictionary` if given.
+    /// Fail if the frame requires a dictionary with another ID or if the window is larger
+    /// than allowed by `options`.
+    pub fn decoding_context(
+        &self,
+        dictionary: Option<&Dictionary>,
+        options: &DecoderOptions,
+    ) -> Result<DecodingContext<'static>> {
+        let mut context = DecodingContext::default();
+        self.reset_context(&mut context, dictionary, options)?;
+
+        Ok(context)
+    }
+
+    /// Reset `context` to decode the frame, keeping its allocations. See
+    /// `Header::decoding_context`.
+    pub fn reset_context(
+        &self,
+        context: &mut DecodingContext,
+        dictionary: Option<&Dictionary>,
+        options: &DecoderOptions,
+    ) -> Result<()> {
+        let required = self.dictionnary_id.filter(|&id| id != 0);
+
+        match (required, dictionary) {
+            (Some(id), None) => {
+                return Err(crate::Error::from(Error::MissingDictionary(id))
+                    .in_section(Section::FrameHeader))
+            }
+            (Some(id), Some(dict)) if id != dict.id as u64 => {
+                return Err(crate::Error::from(Error::DictionaryMismatch {
+                    expected: id,
+                    got: dict.id,
+                })
+                .in_section(Section::FrameHeader))
+            }
+            _ => (),
+        }
+
+        context
+            .reset(self.window_size, options.max_window_size)
+            .map_err(|e| crate::Error::from(e).in_section(Section::FrameHeader))?;
+        if let Some(dict) = dictionary {
+            context.load_dictionary(dict);
+        }
+        context.content_size = self.content_size;
+
+        Ok(())
+    }
+
     fn parse_window_descriptor(input: &mut ForwardByteParser<'_>) -> Result<u64> {
         let window_descriptor = input.u8()?;
         let (mantissa, exponent) = get_n_bits(window_descriptor, 3);
@@ -187,29 +321,109 @@ impl Header {
     }
 }
 
+/// What to do with the content checksum of the frames
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumPolicy {
+    /// Return an error if the checksum does not match
+    #[default]
+    Verify,
+    /// Print a warning on stderr if the checksum does not match, without failing. The
+    /// warning is not printed without `std`.
+    Warn,
+    /// Do not compute the checksum
+    Ignore,
+}
+
+impl ChecksumPolicy {
+    /// Return the hasher to feed with the decoded data if the checksum of the frame
+    /// has to be computed
+    pub fn hasher(self, header: &Header) -> Option<XxHash64> {
+        match self {
+            _ if !header.content_checksum_flag => None,
+            ChecksumPolicy::Ignore => None,
+            _ => Some(XxHash64::with_seed(0)),
+        }
+    }
+
+    /// Compare the checksum computed in `context` with the one read in the frame
+    pub fn check(self, context: &DecodingContext, checksum: u32) -> Result<()> {
+        let Some(hasher) = &context.checksum else {
+            return Ok(());
+        };
+
+        let computed = hasher.finish() as u32;
+        match self {
+            _ if computed == checksum => Ok(()),
+            ChecksumPolicy::Verify => {
+                Err(crate::Error::from(Error::BadCheksum).in_section(Section::Checksum))
+            }
+            ChecksumPolicy::Warn => {
+                #[cfg(feature = "std")]
+                eprintln!("Warning: Bad checksum! Computed {computed:#x}, read {checksum:#x}");
+                Ok(())
+            }
+            ChecksumPolicy::Ignore => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ZStandard<'a> {
     header: Header,
     blocks: Vec<Block<'a>>,
+    /// Raw header of each block, to locate the errors
+    block_headers: Vec<&'a [u8]>,
     checksum: Option<u32>,
+    checksum_policy: ChecksumPolicy,
+    options: DecoderOptions,
+    /// Raw frame, from the frame header to the checksum
+    data: &'a [u8],
 }
 
 impl<'a> ZStandard<'a> {
     pub fn parse(input: &mut ForwardByteParser<'a>) -> Result<Self> {
+        Self::parse_with_options(input, &DecoderOptions::default())
+    }
+
+    /// Parse a frame, enforcing the window size and number of blocks limits of `options`.
+    /// `options` are also used when decoding the frame.
+    pub fn parse_with_options(
+        input: &mut ForwardByteParser<'a>,
+        options: &DecoderOptions,
+    ) -> Result<Self> {
+        let start = input.remaining();
+        Self::parse_frame(input, options).map_err(|e| e.at(start).relative_to(start, 0))
+    }
+
+    fn parse_frame(input: &mut ForwardByteParser<'a>, options: &DecoderOptions) -> Result<Self> {
+        let start = input.remaining();
         let header = Header::parse(input)?;
 
-        if header.window_size > MAX_WIN_SIZE {
-            return Err(Error::WindowSizeTooBig {
-                max: MAX_WIN_SIZE,
-                got: header.window_size,
-            });
+        if header.window_size > options.max_window_size {
+            return Err(
+                crate::Error::from(decoding_context::Error::WindowSizeTooBig {
+                    max: options.max_window_size,
+                    got: header.window_size,
+                })
+                .in_section(Section::FrameHeader),
+            );
         }
 
         let mut blocks: Vec<Block> = vec![];
+        let mut block_headers = vec![];
 
         loop {
-            let (cur, last) = Block::parse(input)?;
+            let block_start = input.remaining();
+            if let Some(max) = options.max_blocks.filter(|&max| blocks.len() >= max) {
+                return Err(crate::Error::from(Error::TooManyBlocks(max))
+                    .in_block(blocks.len())
+                    .at(block_start));
+            }
+
+            let (cur, last) = Block::parse_with_max_size(input, header.block_maximum_size())
+                .map_err(|e| e.in_block(blocks.len()).at(block_start))?;
             blocks.push(cur);
+            block_headers.push(&block_start[..3]);
 
             if last {
                 break;
@@ -217,7 +431,12 @@ impl<'a> ZStandard<'a> {
         }
 
         let checksum = if header.content_checksum_flag {
-            Some(input.le_u32().map_err(Error::MissingChecksum)?)
+            let position = input.remaining();
+            Some(input.le_u32().map_err(|e| {
+                crate::Error::from(Error::MissingChecksum(e))
+                    .in_section(Section::Checksum)
+                    .at(position)
+            })?)
         } else {
             None
         };
@@ -225,38 +444,163 @@ impl<'a> ZStandard<'a> {
         Ok(ZStandard {
             header,
             blocks,
+            block_headers,
             checksum,
+            checksum_policy: ChecksumPolicy::default(),
+            options: *options,
+            data: &start[..start.len() - input.len()],
         })
     }
 
+    /// Set what to do when the content checksum does not match the decoded data
+    pub fn with_checksum_policy(mut self, policy: ChecksumPolicy) -> Self {
+        self.checksum_policy = policy;
+        self
+    }
+
     pub fn decode(self) -> Result<Vec<u8>> {
-        let mut context: DecodingContext = DecodingContext::new(self.header.window_size)?;
+        self.decode_with(None)
+    }
+
+    /// Decode the frame starting from the content and the tables of `dictionary`
+    pub fn decode_with_dictionary(self, dictionary: &Dictionary) -> Result<Vec<u8>> {
+        self.decode_with(Some(dictionary))
+    }
+
+    fn decode_with(self, dictionary: Option<&Dictionary>) -> Result<Vec<u8>> {
+        let mut context = DecodingContext::default();
+        self.decode_in_context(&mut context, dictionary)?;
+
+        Ok(context.decoded.into_vec())
+    }
+
+    /// Decode the frame with `context`, which is reset first so that its allocations are
+    /// reused, and append the decoded content to `output`
+    pub fn decode_with_context(
+        self,
+        context: &mut DecodingContext,
+        dictionary: Option<&Dictionary>,
+        output: &mut Vec<u8>,
+    ) -> Result<()> {
+        self.decode_in_context(context, dictionary)?;
+        output.extend_from_slice(&context.decoded);
+
+        Ok(())
+    }
 
-        for block in self.blocks {
-            block.decode(&mut context)?; // Copying block content, TODO: check if possible other way
+    /// Decode the frame at the start of `dst` with the state of `context`, reset first
+    /// like in `decode_with_context`. The output is not allocated, decoding fails with
+    /// `ErrorKind::DstTooSmall` if it does not fit in `dst`. Return the number of bytes
+    /// written.
+    pub fn decode_into(
+        self,
+        context: &mut DecodingContext,
+        dictionary: Option<&Dictionary>,
+        dst: &mut [u8],
+    ) -> Result<usize> {
+        if let Some(size) = self
+            .header
+            .content_size
+            .filter(|&size| size > dst.len() as u64)
+        {
+            let error = decoding_context::Error::DstTooSmall {
+                requested: size as usize,
+                available: dst.len(),
+            };
+            return Err(self.locate(error.into()));
         }
 
-        if self.header.content_checksum_flag {
-            context.checksum = Some(twox_hash::XxHash64::with_seed(0));
-            if let Some(mut hash) = context.checksum {
-                hash.write(&context.decoded);
-            }
-            let check = context.checksum.unwrap().finish();
-            eprintln!(
-                "Computed: {:#b}, Read: {:#b}",
-                check,
-                self.checksum.unwrap()
-            );
+        let (mut borrowed, owned) = mem::take(context).with_output(Output::borrowed(dst));
+        let result = self.decode_in_context(&mut borrowed, dictionary);
+        let (restored, written) = borrowed.with_output(owned);
+        *context = restored;
 
-            if check as u32 == self.checksum.unwrap() {
-            } else {
-                eprintln!("Warning: Bad checksum !");
-            }
+        result.map(|()| written.len())
+    }
 
-            Ok(context.decoded)
-        } else {
-            Ok(context.decoded)
+    /// Decode the whole frame into `context.decoded`
+    fn decode_in_context(
+        self,
+        context: &mut DecodingContext,
+        dictionary: Option<&Dictionary>,
+    ) -> Result<()> {
+        let data = self.data;
+        self.header
+            .reset_context(context, dictionary, &self.options)
+            .map_err(|e| self.locate(e))?;
+        context.checksum = self.checksum_policy.hasher(&self.header);
+
+        // Each block decodes to at most MAX_BLOCK_SIZE bytes, so a corrupted content
+        // size cannot make us allocate much more than needed
+        if let Some(size) = self.header.content_size {
+            let bound = self.blocks.len() as u64 * MAX_BLOCK_SIZE as u64;
+            context.decoded.reserve_exact(size.min(bound) as usize);
+        }
+
+        self.decode_blocks(context, |_| Ok(()))
+            .map_err(|e| e.at(data).relative_to(data, 0))
+    }
+
+    /// Decode the frame, flushing the output of each block to `sink` so that only the
+    /// window is kept in memory
+    #[cfg(feature = "std")]
+    pub fn decode_to<W: Write>(self, sink: &mut W) -> Result<()> {
+        self.decode_to_with(None, sink)
+    }
+
+    /// Decode the frame starting from `dictionary` like `decode_with_dictionary`,
+    /// flushing the output to `sink` like `decode_to`
+    #[cfg(feature = "std")]
+    pub fn decode_with_dictionary_to<W: Write>(
+        self,
+        dictionary: &Dictionary,
+        sink: &mut W,
+    ) -> Result<()> {
+        self.decode_to_with(Some(dictionary), sink)
+    }
+
+    #[cfg(feature = "std")]
+    fn decode_to_with<W: Write>(self, dictionary: Option<&Dictionary>, sink: &mut W) -> Result<()> {
+        let data = self.data;
+        let mut context = self
+            .header
+            .decoding_context(dictionary, &self.options)
+            .map_err(|e| self.locate(e))?;
+        context.checksum = self.checksum_policy.hasher(&self.header);
+
+        self.decode_blocks(&mut context, |context| Ok(context.flush(sink)?))
+            .map_err(|e| e.at(data).relative_to(data, 0))
+    }
+
+    /// Decode the blocks into `context`, calling `after_block` after each block, and
+    /// verify the content size and checksum
+    fn decode_blocks<F>(self, context: &mut DecodingContext, mut after_block: F) -> Result<()>
+    where
+        F: FnMut(&mut DecodingContext) -> Result<()>,
+    {
+        for (index, (block, header)) in self.blocks.into_iter().zip(self.block_headers).enumerate()
+        {
+            block
+                .decode(context)
+                .and_then(|()| self.header.check_content_size(context.produced, false))
+                .and_then(|()| Ok(self.options.check_output(context.produced)?))
+                .and_then(|()| after_block(context))
+                .map_err(|e| e.in_block(index).at(header))?;
         }
+        self.header.check_content_size(context.produced, true)?;
+
+        if let Some(checksum) = self.checksum {
+            self.checksum_policy
+                .check(context, checksum)
+                .map_err(|e| e.at(&self.data[self.data.len() - 4..]))?;
+        }
+
+        Ok(())
+    }
+
+    /// Locate an error of the whole frame at its header, relatively to the frame header
+    fn locate(&self, error: crate::Error) -> crate::Error {
+        error.at(self.data).relative_to(self.data, 0)
     }
 
     pub fn header(&self) -> &Header {
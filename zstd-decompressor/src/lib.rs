@@ -1,9 +1,30 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(clippy::cast_possible_truncation)]
+extern crate alloc;
+
+#[cfg(feature = "async")]
+pub mod async_reader;
 pub mod block;
 pub mod decoders;
 pub mod decoding_context;
+pub mod decompressor;
+pub mod dictionary;
+mod error;
 pub mod frame;
 pub mod literals;
+pub mod options;
+pub mod output;
+#[cfg(feature = "std")]
+pub mod parallel;
 pub mod parsing;
+#[cfg(feature = "std")]
+pub mod reader;
 pub mod sequences;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "std")]
+pub mod timings;
 mod utils;
+pub mod window;
+
+pub use error::{Error, ErrorKind, Section};
@@ -1,22 +1,30 @@
 use crate::{
     decoders::{self, huffman::HuffmanDecoder},
     decoding_context::DecodingContext,
-    parsing::{self, BackwardBitParser, ForwardBitParser, ForwardByteParser},
+    parsing::{BackwardBitParser, ForwardBitParser, ForwardByteParser},
+    Section,
 };
+use alloc::vec::Vec;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error{"Parsing Error: {0}"}]
-    ParsingError(#[from] parsing::Error),
-    #[error{"Error while building tree: {0}"}]
-    HuffmanTree(#[from] decoders::Error),
     #[error{"No huffman decoder available"}]
     HuffmanDecoderMissing,
     #[error{"Corrupted literals section: sum of streams sizes is too big"}]
     CorruptedStreamsSizeTooBig,
+    #[error{"Corrupted literals section: {got} literals decoded but the regenerated size is {expected}"}]
+    RegeneratedSizeMismatch { expected: usize, got: usize },
+    #[error{"Corrupted literals section: stream {stream} decoded to {got} literals instead of {expected}"}]
+    StreamSizeMismatch {
+        stream: usize,
+        expected: usize,
+        got: usize,
+    },
+    #[error{"Corrupted literals section: regenerated size {0} is too small for 4 streams"}]
+    RegeneratedSizeTooSmall(usize),
 }
 
-type Result<T> = eyre::Result<T, Error>;
+type Result<T> = core::result::Result<T, crate::Error>;
 
 #[derive(Debug)]
 pub enum LiteralsSection<'a> {
@@ -30,7 +38,10 @@ pub enum LiteralsSection<'a> {
     CompressedLiteralsBlock {
         huffman_decoder: Option<HuffmanDecoder>,
         regenerated_size: usize,
-        jump_table: [u16; 4],
+        /// Number of Huffman streams, 1 or 4
+        n_streams: u8,
+        /// Size of each stream, only the first one is used with a single stream
+        jump_table: [usize; 4],
         data: &'a [u8],
     },
 }
@@ -47,12 +58,25 @@ impl<'a> LiteralsSection<'a> {
     /// `context` if appropriate (compressed literals block with a
     /// Huffman table inside).
     pub fn decode(self, context: &mut DecodingContext) -> Result<Vec<u8>> {
+        let mut res = Vec::new();
+        self.decode_into(context, &mut res)?;
+
+        Ok(res)
+    }
+
+    /// Decompress the literals section into `res`, replacing its content. The Huffman tree
+    /// of the section, if any, is allocated when it is parsed, the decoding itself does not
+    /// allocate once `res` holds `regenerated_size` literals.
+    pub fn decode_into(self, context: &mut DecodingContext, res: &mut Vec<u8>) -> Result<()> {
+        res.clear();
+
         match self {
-            LiteralsSection::RawLiteralsBlock { data } => Ok(data.to_owned()),
-            LiteralsSection::RLELiteralsBlock { byte, repeat } => Ok(vec![byte; repeat as usize]),
+            LiteralsSection::RawLiteralsBlock { data } => res.extend_from_slice(data),
+            LiteralsSection::RLELiteralsBlock { byte, repeat } => res.resize(repeat as usize, byte),
             LiteralsSection::CompressedLiteralsBlock {
                 huffman_decoder,
-                regenerated_size: _,
+                regenerated_size,
+                n_streams,
                 jump_table,
                 data,
             } => {
@@ -61,31 +85,169 @@ impl<'a> LiteralsSection<'a> {
                 }
 
                 let decoder = match &context.huffman_decoder {
-                    None => return Err(Error::HuffmanDecoderMissing),
+                    None => {
+                        return Err(crate::Error::from(Error::HuffmanDecoderMissing)
+                            .in_section(Section::HuffmanTree)
+                            .at(data))
+                    }
                     Some(h) => h,
                 };
 
-                let mut res = vec![];
-                let mut data = data;
-                for stream_size in jump_table {
-                    if stream_size == 0 {
-                        break;
-                    }
+                if n_streams == 1 {
+                    res.reserve(regenerated_size);
+                    Self::decode_stream(decoder, data, res)?;
+                } else {
+                    Self::decode_four_streams(decoder, regenerated_size, jump_table, data, res)?;
+                }
+
+                if res.len() != regenerated_size {
+                    return Err(crate::Error::from(Error::RegeneratedSizeMismatch {
+                        expected: regenerated_size,
+                        got: res.len(),
+                    })
+                    .in_section(Section::HuffmanStreams)
+                    .at(data));
+                }
+            }
+        }
 
-                    let mut parser = BackwardBitParser::new(&data[..stream_size as usize])?;
-                    data = &data[stream_size as usize..];
+        Ok(())
+    }
 
-                    while !parser.is_empty() {
-                        res.push(decoder.decode(&mut parser)?);
-                    }
+    /// Decode the 4 Huffman streams of `data` together, each one into its own part of `res`
+    fn decode_four_streams(
+        decoder: &HuffmanDecoder,
+        regenerated_size: usize,
+        jump_table: [usize; 4],
+        data: &[u8],
+        res: &mut Vec<u8>,
+    ) -> Result<()> {
+        // The first 3 streams hold (Regenerated_Size+3)/4 literals each, see
+        // https://datatracker.ietf.org/doc/html/rfc8878#name-jump_table
+        let share = regenerated_size.div_ceil(4);
+        if 3 * share > regenerated_size {
+            return Err(
+                crate::Error::from(Error::RegeneratedSizeTooSmall(regenerated_size))
+                    .in_section(Section::HuffmanStreams)
+                    .at(data),
+            );
+        }
+        if jump_table.iter().sum::<usize>() > data.len() {
+            return Err(crate::Error::from(Error::CorruptedStreamsSizeTooBig)
+                .in_section(Section::HuffmanStreams)
+                .at(data));
+        }
+
+        let mut streams = [data; 4];
+        let mut data = data;
+        for (s, stream_size) in jump_table.into_iter().enumerate() {
+            (streams[s], data) = data.split_at(stream_size);
+        }
+        let parser = |stream| {
+            BackwardBitParser::new(stream).map_err(|e| {
+                crate::Error::from(e)
+                    .in_section(Section::HuffmanStreams)
+                    .at(stream)
+            })
+        };
+        let mut parsers = [
+            parser(streams[0])?,
+            parser(streams[1])?,
+            parser(streams[2])?,
+            parser(streams[3])?,
+        ];
+        let bits = parsers.each_ref().map(BackwardBitParser::len);
+
+        res.resize(regenerated_size, 0);
+        let (first, rest) = res.split_at_mut(share);
+        let (second, rest) = rest.split_at_mut(share);
+        let (third, fourth) = rest.split_at_mut(share);
+        let outputs = [first, second, third, fourth];
+        let mut written = [0; 4];
+
+        // A literal takes at most `max_bits` bits, so every stream can decode `count`
+        // literals without checking for the end of its data or of its output
+        let max_bits = decoder.max_bits().max(1) as usize;
+        loop {
+            let count = (0..4)
+                .map(|s| (outputs[s].len() - written[s]).min(parsers[s].len() / max_bits))
+                .min()
+                .unwrap_or(0);
+            if count == 0 {
+                break;
+            }
+
+            for _ in 0..count {
+                for s in 0..4 {
+                    outputs[s][written[s]] = decoder
+                        .decode(&mut parsers[s])
+                        .map_err(|e| stream_error(e, streams[s], bits[s], &parsers[s]))?;
+                    written[s] += 1;
                 }
+            }
+        }
+
+        for (stream, (parser, output)) in parsers.iter_mut().zip(outputs).enumerate() {
+            let locate = |e, parser: &BackwardBitParser| {
+                stream_error(e, streams[stream], bits[stream], parser)
+            };
+            let mut got = written[stream];
+            while got < output.len() && !parser.is_empty() {
+                output[got] = decoder.decode(parser).map_err(|e| locate(e, parser))?;
+                got += 1;
+            }
+            // Count the extra literals to report them
+            while !parser.is_empty() {
+                decoder.decode(parser).map_err(|e| locate(e, parser))?;
+                got += 1;
+            }
 
-                Ok(res)
+            if got != output.len() {
+                return Err(crate::Error::from(Error::StreamSizeMismatch {
+                    stream,
+                    expected: output.len(),
+                    got,
+                })
+                .in_section(Section::HuffmanStreams)
+                .at(streams[stream]));
             }
         }
+
+        Ok(())
+    }
+
+    /// Decode a whole Huffman stream into `output`, return the number of decoded literals
+    fn decode_stream(
+        decoder: &HuffmanDecoder,
+        stream: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<usize> {
+        let start = output.len();
+        let mut parser = BackwardBitParser::new(stream).map_err(|e| {
+            crate::Error::from(e)
+                .in_section(Section::HuffmanStreams)
+                .at(stream)
+        })?;
+        let bits = parser.len();
+
+        while !parser.is_empty() {
+            let literal = decoder
+                .decode(&mut parser)
+                .map_err(|e| stream_error(e, stream, bits, &parser))?;
+            output.push(literal);
+        }
+
+        Ok(output.len() - start)
     }
 
     pub fn parse(input: &mut ForwardByteParser<'a>) -> Result<Self> {
+        let start = input.remaining();
+        Self::parse_section(input).map_err(|e| e.in_section(Section::LiteralsHeader).at(start))
+    }
+
+    /// Parse the literals section, locating the errors of the Huffman tree and of the
+    /// jump table
+    fn parse_section(input: &mut ForwardByteParser<'a>) -> Result<Self> {
         let (lit_type, regen_size, compressed_size, n_streams) = Self::parse_header(input)?;
 
         match lit_type {
@@ -101,30 +263,26 @@ impl<'a> LiteralsSection<'a> {
                 let tree = if matches!(v, LiteralType::Treeless) {
                     None
                 } else {
-                    Some(HuffmanDecoder::parse(&mut new_input)?)
+                    let start = new_input.remaining();
+                    Some(HuffmanDecoder::parse(&mut new_input).map_err(|e| {
+                        crate::Error::from(e)
+                            .in_section(Section::HuffmanTree)
+                            .at(start)
+                    })?)
                 };
 
-                let total_streams_size = new_input.len();
+                let streams = new_input.remaining();
                 let jump_table = if n_streams == 4 {
-                    let (s1, s2, s3) = (
-                        new_input.le_u16()?,
-                        new_input.le_u16()?,
-                        new_input.le_u16()?,
-                    );
-
-                    if s1 as usize + s2 as usize + s3 as usize > total_streams_size - 6 {
-                        return Err(Error::CorruptedStreamsSizeTooBig);
-                    }
-
-                    let s4 = total_streams_size - 6 - s1 as usize - s2 as usize - s3 as usize;
-                    [s1, s2, s3, s4 as u16]
+                    Self::parse_jump_table(&mut new_input)
+                        .map_err(|e| e.in_section(Section::HuffmanStreams).at(streams))?
                 } else {
-                    [new_input.len() as u16, 0, 0, 0]
+                    [new_input.len(), 0, 0, 0]
                 };
 
                 Ok(LiteralsSection::CompressedLiteralsBlock {
                     huffman_decoder: tree,
                     regenerated_size: regen_size,
+                    n_streams,
                     jump_table,
                     data: new_input.slice(new_input.len())?,
                 })
@@ -132,6 +290,19 @@ impl<'a> LiteralsSection<'a> {
         }
     }
 
+    /// Parse the jump table of 4 streams and return the size of each stream
+    fn parse_jump_table(input: &mut ForwardByteParser<'a>) -> Result<[usize; 4]> {
+        let total_streams_size = input.len();
+        let (s1, s2, s3) = (input.le_u16()?, input.le_u16()?, input.le_u16()?);
+
+        if s1 as usize + s2 as usize + s3 as usize > total_streams_size - 6 {
+            return Err(Error::CorruptedStreamsSizeTooBig.into());
+        }
+
+        let s4 = total_streams_size - 6 - s1 as usize - s2 as usize - s3 as usize;
+        Ok([s1 as usize, s2 as usize, s3 as usize, s4])
+    }
+
     fn parse_header(input: &mut ForwardByteParser<'a>) -> Result<(LiteralType, usize, usize, u8)> {
         let header = input.u8()?;
         let binding = [header];
@@ -205,3 +376,17 @@ impl<'a> LiteralsSection<'a> {
         Ok((lit_type, regen_size, compressed_size, n_streams))
     }
 }
+
+/// Locate the error of the Huffman `stream`, decoded with `parser` which held `bits` bits
+/// at first
+fn stream_error(
+    error: decoders::Error,
+    stream: &[u8],
+    bits: usize,
+    parser: &BackwardBitParser,
+) -> crate::Error {
+    crate::Error::from(error)
+        .in_section(Section::HuffmanStreams)
+        .at(stream)
+        .at_bit((bits - parser.len()) as u64)
+}
@@ -0,0 +1,79 @@
+use crate::frame::{Error, MAX_WIN_SIZE};
+
+/// Largest window size a window descriptor can represent (about 3.75TB), see
+/// https://datatracker.ietf.org/doc/html/rfc8878#name-window-descriptor
+pub const MAX_WINDOW_DESCRIPTOR_SIZE: u64 = (1 << 41) + 7 * (1 << 38);
+
+/// Limits and behaviour of the decoder. The defaults accept windows up to
+/// `MAX_WIN_SIZE`, do not limit the output, the number of blocks or of frames, and
+/// ignore skippable frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderOptions {
+    /// Maximum accepted window size
+    pub max_window_size: u64,
+    /// Maximum number of decoded bytes
+    pub max_output: Option<u64>,
+    /// Maximum number of blocks in a frame
+    pub max_blocks: Option<usize>,
+    /// Maximum number of frames, skippable frames included
+    pub max_frames: Option<usize>,
+    /// Return the skippable frames (or their content) instead of ignoring them
+    pub include_skippable: bool,
+}
+
+impl Default for DecoderOptions {
+    fn default() -> Self {
+        DecoderOptions {
+            max_window_size: MAX_WIN_SIZE,
+            max_output: None,
+            max_blocks: None,
+            max_frames: None,
+            include_skippable: false,
+        }
+    }
+}
+
+impl DecoderOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject frames whose window is larger than `size` bytes. Use
+    /// `MAX_WINDOW_DESCRIPTOR_SIZE` to accept every window a descriptor can represent.
+    pub fn max_window_size(mut self, size: u64) -> Self {
+        self.max_window_size = size;
+        self
+    }
+
+    /// Fail once more than `size` bytes have been decoded
+    pub fn max_output(mut self, size: u64) -> Self {
+        self.max_output = Some(size);
+        self
+    }
+
+    /// Reject frames with more than `count` blocks
+    pub fn max_blocks(mut self, count: usize) -> Self {
+        self.max_blocks = Some(count);
+        self
+    }
+
+    /// Fail on inputs with more than `count` frames
+    pub fn max_frames(mut self, count: usize) -> Self {
+        self.max_frames = Some(count);
+        self
+    }
+
+    /// Return the skippable frames (or their content) instead of ignoring them
+    pub fn include_skippable(mut self, include: bool) -> Self {
+        self.include_skippable = include;
+        self
+    }
+
+    /// Check that `size` decoded bytes do not exceed the maximum output size
+    pub fn check_output(&self, size: u64) -> Result<(), Error> {
+        match self.max_output {
+            Some(max) if size > max => Err(Error::OutputTooBig(max)),
+            _ => Ok(()),
+        }
+    }
+}
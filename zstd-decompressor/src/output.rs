@@ -0,0 +1,134 @@
+use alloc::vec::Vec;
+use core::ops::{Deref, Range};
+
+use crate::decoding_context::Error;
+
+/// Buffer receiving the decoded content, either owned and growing as needed or borrowed
+/// from the caller with a fixed size.
+///
+/// Writers must call `check_space` before writing, writing past the end of a borrowed
+/// buffer panics.
+#[derive(Debug)]
+pub enum Output<'a> {
+    Owned(Vec<u8>),
+    Borrowed {
+        dst: &'a mut [u8],
+        /// Number of bytes written at the start of `dst`
+        len: usize,
+    },
+}
+
+impl Default for Output<'_> {
+    fn default() -> Self {
+        Output::Owned(Vec::new())
+    }
+}
+
+impl<'a> Output<'a> {
+    /// Output writing to the start of `dst`, never allocating
+    pub fn borrowed(dst: &'a mut [u8]) -> Self {
+        Output::Borrowed { dst, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Output::Owned(data) => data.len(),
+            Output::Borrowed { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check that `additional` bytes can be written, always true for an owned buffer
+    #[inline]
+    pub fn check_space(&self, additional: usize) -> Result<(), Error> {
+        match self {
+            Output::Borrowed { dst, len } if dst.len() - len < additional => {
+                Err(Error::DstTooSmall {
+                    requested: additional,
+                    available: dst.len() - len,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reserve space for `additional` bytes in an owned buffer
+    pub fn reserve(&mut self, additional: usize) {
+        if let Output::Owned(data) = self {
+            data.reserve(additional);
+        }
+    }
+
+    /// Reserve space for exactly `additional` bytes in an owned buffer
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if let Output::Owned(data) = self {
+            data.reserve_exact(additional);
+        }
+    }
+
+    #[inline]
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        match self {
+            Output::Owned(output) => output.extend_from_slice(data),
+            Output::Borrowed { dst, len } => {
+                dst[*len..*len + data.len()].copy_from_slice(data);
+                *len += data.len();
+            }
+        }
+    }
+
+    /// Append the bytes of `range`, which must already be written
+    #[inline]
+    pub fn extend_from_within(&mut self, range: Range<usize>) {
+        match self {
+            Output::Owned(output) => output.extend_from_within(range),
+            Output::Borrowed { dst, len } => {
+                assert!(range.end <= *len);
+                let count = range.len();
+                dst.copy_within(range, *len);
+                *len += count;
+            }
+        }
+    }
+
+    /// Append `count` times `byte`
+    pub fn extend_repeated(&mut self, byte: u8, count: usize) {
+        match self {
+            Output::Owned(output) => output.resize(output.len() + count, byte),
+            Output::Borrowed { dst, len } => {
+                dst[*len..*len + count].fill(byte);
+                *len += count;
+            }
+        }
+    }
+
+    /// Forget the written bytes, keeping the buffer
+    pub fn clear(&mut self) {
+        match self {
+            Output::Owned(output) => output.clear(),
+            Output::Borrowed { len, .. } => *len = 0,
+        }
+    }
+
+    /// Written bytes, copied if the buffer is borrowed
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            Output::Owned(output) => output,
+            Output::Borrowed { dst, len } => dst[..len].to_vec(),
+        }
+    }
+}
+
+impl Deref for Output<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Output::Owned(output) => output,
+            Output::Borrowed { dst, len } => &dst[..*len],
+        }
+    }
+}
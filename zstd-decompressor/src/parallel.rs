@@ -0,0 +1,168 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+use crate::{
+    decoding_context::DecodingContext,
+    dictionary::Dictionary,
+    frame::{ChecksumPolicy, Frame},
+    options::DecoderOptions,
+    parsing::ForwardByteParser,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error{"A decoding thread panicked"}]
+    ThreadPanicked,
+    #[error{"The decoding threads stopped before decoding every frame"}]
+    ThreadsStopped,
+}
+
+type Result<T> = core::result::Result<T, crate::Error>;
+
+/// Number of frames decoded or waiting to be written for each thread
+const FRAMES_PER_THREAD: usize = 2;
+
+/// Decompressor decoding the frames of an input on several threads.
+///
+/// Frames are independent, so once their boundaries are known they can be decoded in any
+/// order. The output is still written in order, and at most a few frames per thread are
+/// kept in memory at once.
+pub struct ParallelDecompressor {
+    threads: usize,
+    options: DecoderOptions,
+    checksum_policy: ChecksumPolicy,
+    dictionary: Option<Dictionary>,
+}
+
+impl ParallelDecompressor {
+    /// Decompressor using `threads` threads, or one per core if `threads` is 0
+    pub fn new(threads: usize) -> Self {
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            n => n,
+        };
+
+        ParallelDecompressor {
+            threads,
+            options: DecoderOptions::default(),
+            checksum_policy: ChecksumPolicy::default(),
+            dictionary: None,
+        }
+    }
+
+    /// Set the limits of the decoder
+    pub fn with_options(mut self, options: DecoderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set what to do when the content checksum of a frame does not match
+    pub fn with_checksum_policy(mut self, policy: ChecksumPolicy) -> Self {
+        self.checksum_policy = policy;
+        self
+    }
+
+    /// Decode frames starting from `dictionary`
+    pub fn with_dictionary(mut self, dictionary: Dictionary) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    /// Number of threads decoding frames
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Decompress all the frames of `input` and write their content to `output`, in order.
+    /// Return the number of bytes written.
+    pub fn decompress_to<W: Write>(&self, input: &[u8], output: &mut W) -> Result<u64> {
+        let (job_sender, job_receiver) = mpsc::channel::<(usize, usize, Frame)>();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let job_receiver = Mutex::new(job_receiver);
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads {
+                let job_receiver = &job_receiver;
+                let result_sender = result_sender.clone();
+                scope.spawn(move || {
+                    let mut context = DecodingContext::default();
+                    loop {
+                        // The lock is released as soon as a job is received
+                        let job = job_receiver.lock().unwrap().recv();
+                        let Ok((index, frame_index, frame)) = job else {
+                            break;
+                        };
+                        // A panic is reported as the result of the frame, and stops the
+                        // thread as its context may be inconsistent
+                        let decoded = panic::catch_unwind(AssertUnwindSafe(|| {
+                            self.decode_frame(&mut context, frame)
+                        }));
+                        let panicked = decoded.is_err();
+                        let decoded = decoded
+                            .unwrap_or_else(|_| Err(Error::ThreadPanicked.into()))
+                            .map_err(|e| e.in_frame(frame_index).relative_to(input, 0));
+                        if result_sender.send((index, decoded)).is_err() || panicked {
+                            break;
+                        }
+                    }
+                });
+            }
+            // Receiving fails once every thread stopped
+            drop(result_sender);
+
+            // Dropping the job sender on return, errors included, stops the threads
+            let job_sender = job_sender;
+            let mut frames = ForwardByteParser::new(input).iter_with_options(self.options);
+            let mut pending = BTreeMap::new();
+            let (mut sent, mut written, mut total) = (0, 0, 0);
+
+            loop {
+                while sent - written < self.threads * FRAMES_PER_THREAD {
+                    match frames.next() {
+                        Some(frame) => {
+                            let job = (sent, frames.frames - 1, frame?);
+                            job_sender.send(job).map_err(|_| Error::ThreadsStopped)?;
+                            sent += 1;
+                        }
+                        None => break,
+                    }
+                }
+                if written == sent {
+                    break;
+                }
+
+                let (index, decoded) = result_receiver.recv().map_err(|_| Error::ThreadsStopped)?;
+                pending.insert(index, decoded);
+                while let Some(decoded) = pending.remove(&written) {
+                    let decoded = decoded?;
+                    total += decoded.len() as u64;
+                    self.options.check_output(total)?;
+                    output.write_all(&decoded)?;
+                    written += 1;
+                }
+            }
+
+            Ok(total)
+        })
+    }
+
+    /// Decode a single frame with `context`, kept by the thread from one frame to the next
+    fn decode_frame(&self, context: &mut DecodingContext, frame: Frame) -> Result<Vec<u8>> {
+        match frame {
+            Frame::ZStandardFrame(frame) => {
+                let mut decoded = Vec::new();
+                frame
+                    .with_checksum_policy(self.checksum_policy)
+                    .decode_with_context(context, self.dictionary.as_ref(), &mut decoded)?;
+                Ok(decoded)
+            }
+            Frame::SkippableFrame(skippable) => Ok(skippable.data.to_vec()),
+        }
+    }
+}
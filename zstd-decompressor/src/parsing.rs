@@ -1,10 +1,4 @@
-use std::u8;
-
-use bitbuffer::{BigEndian, BitReadBuffer, LittleEndian};
-use eyre;
-use thiserror;
-
-use crate::{frame, utils::int_from_array};
+use crate::{frame, options::DecoderOptions, utils::int_from_array};
 
 pub struct ForwardByteParser<'a>(&'a [u8]);
 
@@ -16,23 +10,34 @@ pub enum Error {
     NotEnoughBits { requested: usize, available: usize },
     #[error{"Maximum readable bits (64) exceded: requested {0}."}]
     MaximumReadableBitsExceeded(usize),
+    #[error{"Maximum peekable bits (56) exceeded: requested {0}."}]
+    MaximumPeekableBitsExceeded(usize),
     #[error{"Given data is empty."}]
     EmptyInputData,
     #[error{"The first byte is null"}]
     NullByte,
-    #[error{"Asking for an empty slice"}]
-    EmptySliceError,
 }
 
-pub type Result<T, E = Error> = eyre::Result<T, E>;
+pub type Result<T, E = Error> = core::result::Result<T, E>;
 
 impl<'a> ForwardByteParser<'a> {
     pub fn new(data: &'a [u8]) -> Self {
         Self(data)
     }
 
+    /// Iterate over all the frames, skippable frames included
     pub fn iter(self) -> frame::FrameIterator<'a> {
-        frame::FrameIterator { parser: self }
+        self.iter_with_options(DecoderOptions::default().include_skippable(true))
+    }
+
+    /// Iterate over the frames, enforcing the limits of `options`
+    pub fn iter_with_options(self, options: DecoderOptions) -> frame::FrameIterator<'a> {
+        frame::FrameIterator {
+            input: self.0,
+            parser: self,
+            options,
+            frames: 0,
+        }
     }
 
     /// Retrieve the next byte unparsed
@@ -59,12 +64,14 @@ impl<'a> ForwardByteParser<'a> {
         self.0.is_empty()
     }
 
-    /// Extract `len` bytes as a slice
+    /// Bytes still unparsed, without consuming them
+    pub fn remaining(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Extract `len` bytes as a slice, which may be empty
     pub fn slice(&mut self, len: usize) -> Result<&'a [u8]> {
         let old_len = self.len();
-        if len == 0 {
-            return Err(Error::EmptySliceError);
-        }
         if old_len < len {
             // Case where there are fewer bytes available than len
             return Err(Error::NotEnoughBytes {
@@ -111,18 +118,42 @@ impl<'a> ForwardByteParser<'a> {
     }
 }
 
+/// Number of bits which can be peeked at once: after a refill, the accumulator holds at
+/// least this many bits unless the input is exhausted
+pub const MAX_PEEK_BITS: usize = 56;
+
+/// Keep the `len` lowest bits of `value`
+fn low_bits(value: u64, len: usize) -> u64 {
+    if len == 0 {
+        0
+    } else {
+        value & (u64::MAX >> (64 - len))
+    }
+}
+
+/// Read at most 8 bytes as a little-endian integer
+fn load_le(bytes: &[u8]) -> u64 {
+    match bytes.try_into() {
+        Ok(word) => u64::from_le_bytes(word),
+        Err(_) => int_from_array(bytes),
+    }
+}
+
 pub struct ForwardBitParser<'a> {
-    data: BitReadBuffer<'a, LittleEndian>,
-    readable: usize,
-    pos: usize,
+    /// Bytes not loaded in the accumulator yet
+    data: &'a [u8],
+    /// Bits to read next, starting from the least significant one
+    acc: u64,
+    /// Number of bits available in `acc`
+    acc_bits: usize,
+    /// Size of the whole input in bytes
+    size: usize,
 }
 
 impl<'a> ForwardBitParser<'a> {
     /// Will return the number of bytes that were read, including the one being read it it was not fully read
     pub fn bytes_read(&self) -> usize {
-        let partially_consumed = usize::from(self.pos % 8 > 0);
-
-        self.pos / 8 + partially_consumed
+        (self.size * 8 - self.len()).div_ceil(8)
     }
 }
 
@@ -132,25 +163,50 @@ impl<'a> ForwardBitParser<'a> {
         if data.is_empty() {
             return Err(Error::EmptyInputData);
         }
-        Ok(ForwardBitParser {
-            data: BitReadBuffer::new(data, LittleEndian),
-            readable: data.len() * 8,
-            pos: 0,
-        })
+
+        let mut parser = ForwardBitParser {
+            data,
+            acc: 0,
+            acc_bits: 0,
+            size: data.len(),
+        };
+        parser.refill();
+
+        Ok(parser)
+    }
+
+    /// Load as many whole bytes as possible in the accumulator
+    fn refill(&mut self) {
+        let count = ((64 - self.acc_bits) / 8).min(self.data.len());
+        if count == 0 {
+            return;
+        }
+
+        let (bytes, rest) = self.data.split_at(count);
+        self.acc |= load_le(bytes) << self.acc_bits;
+        self.acc_bits += 8 * count;
+        self.data = rest;
+    }
+
+    /// Drop `len` bits of the accumulator, `len` being at most `acc_bits`
+    fn skip(&mut self, len: usize) {
+        self.acc = self.acc.checked_shr(len as u32).unwrap_or(0);
+        self.acc_bits -= len;
+        self.refill();
     }
 
     pub fn len(&self) -> usize {
-        self.readable
+        self.acc_bits + 8 * self.data.len()
     }
 
     /// True if there are no bits left to read
     pub fn is_empty(&self) -> bool {
-        self.readable == 0
+        self.len() == 0
     }
 
     /// Get the given number of bits, or return an error.
     pub fn take(&mut self, len: usize) -> Result<u64> {
-        if self.data.bit_len() - self.pos < len {
+        if self.len() < len {
             return Err(Error::NotEnoughBits {
                 requested: len,
                 available: self.len(),
@@ -160,73 +216,123 @@ impl<'a> ForwardBitParser<'a> {
             return Err(Error::MaximumReadableBitsExceeded(len));
         }
 
-        // we verified up there the conditions, can not fail
-        let res = self.data.read_int(self.pos, len).unwrap();
+        if len > self.acc_bits {
+            let low = self.take(32)?;
+            let high = self.take(len - 32)?;
+            return Ok(low | high << 32);
+        }
 
-        self.readable -= len;
-        self.pos += len;
+        let res = low_bits(self.acc, len);
+        self.skip(len);
 
         Ok(res)
     }
 
     /// Peek at next len bits without consuming them
     pub fn peek(&self, len: usize) -> Result<u64> {
-        if self.data.bit_len() - self.pos < len {
+        if self.len() < len {
             return Err(Error::NotEnoughBits {
                 requested: len,
-                available: self.data.bit_len(),
+                available: self.len(),
             });
         }
-        if len > 64 {
-            return Err(Error::MaximumReadableBitsExceeded(len));
+        if len > MAX_PEEK_BITS {
+            return Err(Error::MaximumPeekableBitsExceeded(len));
+        }
+
+        Ok(low_bits(self.acc, len))
+    }
+
+    /// Skip the next `len` bits, usually after peeking at them
+    pub fn consume(&mut self, mut len: usize) -> Result<()> {
+        if self.len() < len {
+            return Err(Error::NotEnoughBits {
+                requested: len,
+                available: self.len(),
+            });
         }
 
-        // we verified up there the conditions, can not fail
-        let res = self.data.read_int(self.pos, len).unwrap();
+        while len > 0 {
+            let skipped = len.min(self.acc_bits);
+            self.skip(skipped);
+            len -= skipped;
+        }
 
-        Ok(res)
+        Ok(())
     }
 }
 
-pub struct BackwardBitParser {
-    data: Vec<u8>,
-    readable: usize,
-    pos: usize,
+/// Bit parser reading its input from the end, as needed by FSE and Huffman streams
+pub struct BackwardBitParser<'a> {
+    /// Bytes not loaded in the accumulator yet, loaded from the end
+    data: &'a [u8],
+    /// Bits to read next, starting from bit `acc_bits - 1`. Bits above it are garbage.
+    acc: u64,
+    /// Number of bits available in `acc`
+    acc_bits: usize,
 }
 
-impl BackwardBitParser {
+impl<'a> BackwardBitParser<'a> {
     /// Create a new backward bit parser. The header is skipped automatically or
     /// an error is returned if the initial 1 cannot be found in the first 8 bits.
-    pub fn new(data: &[u8]) -> Result<Self> {
-        if data.is_empty() {
-            return Err(Error::EmptyInputData);
-        }
-        if data.last().unwrap() == &0 {
-            return Err(Error::NullByte);
-        }
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        let padding = match data.last() {
+            None => return Err(Error::EmptyInputData),
+            Some(0) => return Err(Error::NullByte),
+            Some(last) => last.leading_zeros() as usize + 1,
+        };
 
-        let data = data.iter().rev().copied().collect::<Vec<u8>>();
-        let mut i = 1;
+        let mut parser = BackwardBitParser {
+            data,
+            acc: 0,
+            acc_bits: 0,
+        };
+        parser.refill();
+        parser.skip(padding);
 
-        while data[0] & (1 << (8 - i)) == 0 {
-            i += 1;
+        Ok(parser)
+    }
+
+    /// Load as many whole bytes as possible in the accumulator
+    fn refill(&mut self) {
+        let count = ((64 - self.acc_bits) / 8).min(self.data.len());
+        if count == 0 {
+            return;
         }
 
-        Ok(BackwardBitParser {
-            readable: data.len() * 8 - i,
-            data,
-            pos: i,
-        })
+        let (rest, bytes) = self.data.split_at(self.data.len() - count);
+        let bytes = load_le(bytes);
+        self.acc = if count == 8 {
+            bytes
+        } else {
+            self.acc << (8 * count) | bytes
+        };
+        self.acc_bits += 8 * count;
+        self.data = rest;
+    }
+
+    /// Drop `len` bits of the accumulator, `len` being at most `acc_bits`
+    fn skip(&mut self, len: usize) {
+        self.acc_bits -= len;
+        self.refill();
+    }
+
+    /// Next `len` bits of the accumulator, `len` being at most `acc_bits`
+    fn peek_bits(&self, len: usize) -> u64 {
+        if len == 0 {
+            return 0;
+        }
+        low_bits(self.acc >> (self.acc_bits - len), len)
     }
 
     /// True if there are no bits left to read
     pub fn is_empty(&self) -> bool {
-        self.readable == 0
+        self.len() == 0
     }
 
     /// Get the given number of bits, or return an error.
     pub fn take(&mut self, len: usize) -> Result<u64> {
-        if self.data.len() * 8 - self.pos < len {
+        if self.len() < len {
             return Err(Error::NotEnoughBits {
                 requested: len,
                 available: self.len(),
@@ -236,24 +342,64 @@ impl BackwardBitParser {
             return Err(Error::MaximumReadableBitsExceeded(len));
         }
 
-        if len == 0 {
-            // To prevent bug
-            return Ok(0);
+        if len > self.acc_bits {
+            let high = self.take(len - 32)?;
+            let low = self.take(32)?;
+            return Ok(high << 32 | low);
         }
 
-        // we verified up there the conditions, can not fail
+        let res = self.peek_bits(len);
+        self.skip(len);
 
-        let res = BitReadBuffer::new(&self.data, BigEndian)
-            .read_int(self.pos, len)
-            .unwrap();
+        Ok(res)
+    }
 
-        self.readable -= len;
-        self.pos += len;
+    /// Peek at next len bits without consuming them
+    pub fn peek(&self, len: usize) -> Result<u64> {
+        if self.len() < len {
+            return Err(Error::NotEnoughBits {
+                requested: len,
+                available: self.len(),
+            });
+        }
 
-        Ok(res)
+        self.peek_padded(len)
+    }
+
+    /// Get the next `len` bits without consuming them. If fewer bits are left, they
+    /// are completed with zeroes on the right.
+    pub fn peek_padded(&self, len: usize) -> Result<u64> {
+        if len > MAX_PEEK_BITS {
+            return Err(Error::MaximumPeekableBitsExceeded(len));
+        }
+
+        // The accumulator only lacks bits once the input is exhausted
+        if len > self.acc_bits {
+            return Ok(low_bits(self.acc, self.acc_bits) << (len - self.acc_bits));
+        }
+
+        Ok(self.peek_bits(len))
+    }
+
+    /// Skip the next `len` bits, usually after peeking at them
+    pub fn consume(&mut self, mut len: usize) -> Result<()> {
+        if self.len() < len {
+            return Err(Error::NotEnoughBits {
+                requested: len,
+                available: self.len(),
+            });
+        }
+
+        while len > 0 {
+            let skipped = len.min(self.acc_bits);
+            self.skip(skipped);
+            len -= skipped;
+        }
+
+        Ok(())
     }
 
     pub fn len(&self) -> usize {
-        self.readable
+        self.acc_bits + 8 * self.data.len()
     }
 }
@@ -0,0 +1,352 @@
+use std::io::{self, BufRead, Read};
+
+use crate::{
+    block::Block,
+    decoding_context::DecodingContext,
+    dictionary::Dictionary,
+    frame::{self, ChecksumPolicy, Header, MAGIC_SKIP, MAGIC_ZSTD},
+    options::DecoderOptions,
+    parsing::ForwardByteParser,
+    Error,
+};
+
+/// Size of the chunks used to read the content of skippable frames
+const SKIPPABLE_CHUNK_SIZE: usize = 1 << 16;
+
+enum State {
+    /// Waiting for the magic number of the next frame
+    FrameStart,
+    /// Decoding the blocks of a ZStandard frame
+    Blocks {
+        header: Header,
+        last_block: bool,
+        /// Number of blocks decoded so far in this frame
+        blocks: usize,
+    },
+    /// Reading the content of a skippable frame
+    Skippable { remaining: usize },
+    /// The underlying reader is exhausted
+    Done,
+}
+
+/// Streaming decoder pulling compressed bytes from `R` as they are needed.
+///
+/// Only the current block and the window of the current frame are kept in memory.
+/// Headers are read a few bytes at a time, so `R` should be buffered (e.g. with a
+/// `std::io::BufReader`) if small reads are expensive.
+pub struct ZstdReader<R: Read> {
+    reader: R,
+    state: State,
+    /// Decoding context of the current frame, reused from one frame to the next
+    context: DecodingContext<'static>,
+    /// Raw content of the block being decoded, header included
+    block: Vec<u8>,
+    /// Content of the skippable frame being read
+    skippable: Vec<u8>,
+    /// Position of the next byte to output in the current output buffer
+    pos: usize,
+    /// Number of frames started so far, skippable frames included
+    frames: usize,
+    /// Number of bytes output so far
+    output: u64,
+    /// Number of bytes read from `reader` so far, to locate the errors
+    position: u64,
+    options: DecoderOptions,
+    dictionary: Option<Dictionary>,
+    checksum_policy: ChecksumPolicy,
+}
+
+impl<R: Read> ZstdReader<R> {
+    pub fn new(reader: R) -> Self {
+        ZstdReader {
+            reader,
+            state: State::FrameStart,
+            context: DecodingContext::default(),
+            block: Vec::new(),
+            skippable: Vec::new(),
+            pos: 0,
+            frames: 0,
+            output: 0,
+            position: 0,
+            options: DecoderOptions::default(),
+            dictionary: None,
+            checksum_policy: ChecksumPolicy::default(),
+        }
+    }
+
+    /// Output the content of skippable frames as well, they are ignored by default
+    pub fn include_skippable(mut self, include: bool) -> Self {
+        self.options.include_skippable = include;
+        self
+    }
+
+    /// Set the limits of the decoder
+    pub fn with_options(mut self, options: DecoderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Decode frames starting from `dictionary`
+    pub fn with_dictionary(mut self, dictionary: Dictionary) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    /// Set what to do when the content checksum of a frame does not match
+    pub fn with_checksum_policy(mut self, policy: ChecksumPolicy) -> Self {
+        self.checksum_policy = policy;
+        self
+    }
+
+    /// Get back the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Read the magic number of the next frame, or return `None` if the reader is
+    /// exhausted before the first byte
+    fn read_magic(&mut self) -> io::Result<Option<u32>> {
+        let mut magic = [0u8; 4];
+        let mut filled = 0;
+
+        while filled < magic.len() {
+            match self.reader.read(&mut magic[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+        self.position += magic.len() as u64;
+
+        Ok(Some(u32::from_le_bytes(magic)))
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut value = [0u8; 4];
+        self.reader.read_exact(&mut value)?;
+        self.position += value.len() as u64;
+
+        Ok(u32::from_le_bytes(value))
+    }
+
+    /// Read the next frame header and set the state accordingly
+    fn start_frame(&mut self) -> io::Result<()> {
+        let frame_start = self.position;
+        let magic = match self.read_magic()? {
+            None => {
+                self.state = State::Done;
+                return Ok(());
+            }
+            Some(magic) => magic,
+        };
+
+        let index = self.frames;
+        self.frames += 1;
+        if let Some(max) = self.options.max_frames {
+            if self.frames > max {
+                let error = Error::from(frame::Error::TooManyFrames(max));
+                return Err(invalid_data(error.in_frame(index).at_offset(frame_start)));
+            }
+        }
+
+        self.state = match magic {
+            MAGIC_ZSTD => {
+                let mut raw = vec![0u8; 1];
+                self.reader.read_exact(&mut raw)?;
+                raw.resize(Header::size(raw[0]), 0);
+                self.reader.read_exact(&mut raw[1..])?;
+                let header_start = self.position;
+                self.position += raw.len() as u64;
+
+                let locate = |e: Error| {
+                    invalid_data(e.in_frame(index).at(&raw).relative_to(&raw, header_start))
+                };
+                let header = Header::parse(&mut ForwardByteParser::new(&raw)).map_err(locate)?;
+                header
+                    .reset_context(&mut self.context, self.dictionary.as_ref(), &self.options)
+                    .map_err(locate)?;
+                self.context.checksum = self.checksum_policy.hasher(&header);
+
+                State::Blocks {
+                    header,
+                    last_block: false,
+                    blocks: 0,
+                }
+            }
+            v if v ^ MAGIC_SKIP <= 0x0F => State::Skippable {
+                remaining: self.read_u32()? as usize,
+            },
+            _ => {
+                let error = Error::from(frame::Error::UnrecognizedMagic(magic));
+                return Err(invalid_data(error.in_frame(index).at_offset(frame_start)));
+            }
+        };
+        self.skippable.clear();
+        self.pos = 0;
+
+        Ok(())
+    }
+
+    /// Read and decode the next block of the current frame into `context`. `locate`
+    /// turns the errors into `io::Error`, given the raw block in which they happened.
+    fn decode_block<F>(
+        reader: &mut R,
+        block: &mut Vec<u8>,
+        header: &Header,
+        context: &mut DecodingContext,
+        locate: F,
+    ) -> io::Result<bool>
+    where
+        F: Fn(Error, &[u8]) -> io::Error,
+    {
+        block.resize(3, 0);
+        reader.read_exact(block)?;
+
+        let (_, block_type, block_size) = Block::parse_header(&mut ForwardByteParser::new(block))
+            .map_err(|e| locate(e, block))?;
+        let content_size = Block::content_size(block_type, block_size, header.block_maximum_size())
+            .map_err(|e| locate(e, block))?;
+
+        block.resize(3 + content_size, 0);
+        reader.read_exact(&mut block[3..])?;
+
+        let block: &[u8] = block;
+        let (parsed, last) = Block::parse_with_max_size(
+            &mut ForwardByteParser::new(block),
+            header.block_maximum_size(),
+        )
+        .map_err(|e| locate(e, block))?;
+        parsed
+            .decode(context)
+            .and_then(|()| header.check_content_size(context.produced, last))
+            .map_err(|e| locate(e, block))?;
+
+        Ok(last)
+    }
+}
+
+impl<R: Read> BufRead for ZstdReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        loop {
+            match &mut self.state {
+                State::FrameStart => self.start_frame()?,
+                State::Blocks {
+                    header,
+                    last_block,
+                    blocks,
+                } => {
+                    if self.pos < self.context.decoded.len() {
+                        break;
+                    }
+
+                    let index = self.frames - 1;
+                    if *last_block {
+                        if header.content_checksum_flag {
+                            let mut checksum = [0u8; 4];
+                            self.reader.read_exact(&mut checksum)?;
+                            self.checksum_policy
+                                .check(&self.context, u32::from_le_bytes(checksum))
+                                .map_err(|e| {
+                                    invalid_data(e.in_frame(index).at_offset(self.position))
+                                })?;
+                            self.position += checksum.len() as u64;
+                        }
+                        self.state = State::FrameStart;
+                        continue;
+                    }
+
+                    // Everything was output already, only keep the window
+                    self.context.flush(&mut io::sink())?;
+                    self.pos = 0;
+
+                    let block_index = *blocks;
+                    let block_start = self.position;
+                    let locate = |e: Error, block: &[u8]| {
+                        let e = e.in_frame(index).in_block(block_index).at(block);
+                        invalid_data(e.relative_to(block, block_start))
+                    };
+
+                    *blocks += 1;
+                    if let Some(max) = self.options.max_blocks {
+                        if *blocks > max {
+                            return Err(locate(frame::Error::TooManyBlocks(max).into(), &[]));
+                        }
+                    }
+
+                    *last_block = Self::decode_block(
+                        &mut self.reader,
+                        &mut self.block,
+                        header,
+                        &mut self.context,
+                        locate,
+                    )?;
+                    self.position += self.block.len() as u64;
+                    self.output += self.context.decoded.len() as u64;
+                    self.options
+                        .check_output(self.output)
+                        .map_err(|e| locate(e.into(), &self.block))?;
+                }
+                State::Skippable { remaining } => {
+                    if self.pos < self.skippable.len() {
+                        break;
+                    }
+                    if *remaining == 0 {
+                        self.state = State::FrameStart;
+                        continue;
+                    }
+
+                    let chunk = (*remaining).min(SKIPPABLE_CHUNK_SIZE);
+                    *remaining -= chunk;
+                    if self.options.include_skippable {
+                        self.skippable.resize(chunk, 0);
+                        self.reader.read_exact(&mut self.skippable)?;
+                        self.position += chunk as u64;
+                        self.pos = 0;
+                        self.output += chunk as u64;
+                        self.options
+                            .check_output(self.output)
+                            .map_err(invalid_data)?;
+                    } else {
+                        let skipped =
+                            io::copy(&mut (&mut self.reader).take(chunk as u64), &mut io::sink())?;
+                        if skipped < chunk as u64 {
+                            return Err(io::ErrorKind::UnexpectedEof.into());
+                        }
+                        self.position += skipped;
+                    }
+                }
+                State::Done => break,
+            }
+        }
+
+        Ok(match &self.state {
+            State::Blocks { .. } => &self.context.decoded[self.pos..],
+            State::Skippable { .. } => &self.skippable[self.pos..],
+            _ => &[],
+        })
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+impl<R: Read> Read for ZstdReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+
+        Ok(len)
+    }
+}
+
+pub(crate) fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
@@ -1,28 +1,27 @@
+use alloc::vec::Vec;
+
 use crate::{
     decoders::{
         self,
-        fse::{FseDecoder, FseTable},
+        fse::{FseDecoder, FseTable, State},
         rle::RLEDecoder,
-        sequence::SequenceDecoder,
+        sequence::{CodeDecoder, SequenceDecoder},
         BitDecoder,
     },
     decoding_context::DecodingContext,
-    parsing::{self, BackwardBitParser, ForwardBitParser, ForwardByteParser},
+    parsing::{BackwardBitParser, ForwardBitParser, ForwardByteParser},
+    Section,
 };
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error{"Parsing error: {0}"}]
-    ParsingError(#[from] parsing::Error),
     #[error{"Corrupted data: reserved field set."}]
     ReservedSet,
-    #[error{"Error in decoder: {0}"}]
-    DecoderError(#[from] decoders::Error),
     #[error{"Corrupted data: repeat sequence mode with no previous decoder"}]
     NoPreviousDecoder,
 }
 
-type Result<T> = eyre::Result<T, Error>;
+type Result<T> = core::result::Result<T, crate::Error>;
 
 /// Default distribution for Literals Length, Offset and Match Length
 /// Used to constrcut table for the corresponding the FSE Encoder
@@ -38,6 +37,20 @@ const MATCH_LENGTH_DISTRI: [i16; 53] = [
     1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
 ];
 
+/// States of the predefined tables, built at compile time
+static LITERALS_LENGTH_STATES: [State; 64] = FseTable::const_states(&LITERALS_LENGTH_DISTRI);
+static OFFSET_STATES: [State; 32] = FseTable::const_states(&OFFSET_DISTRI);
+static MATCH_LENGTH_STATES: [State; 64] = FseTable::const_states(&MATCH_LENGTH_DISTRI);
+
+/// States of the predefined table for `code_type`
+fn predefined_states(code_type: &CodeType) -> &'static [State] {
+    match code_type {
+        CodeType::LiteralsLength => &LITERALS_LENGTH_STATES,
+        CodeType::Offset => &OFFSET_STATES,
+        CodeType::MatchLength => &MATCH_LENGTH_STATES,
+    }
+}
+
 #[derive(Debug)]
 pub struct Sequences<'a> {
     pub number_of_sequences: usize,
@@ -50,6 +63,12 @@ pub struct Sequences<'a> {
 impl<'a> Sequences<'a> {
     /// Parse the sequences data from the stream
     pub fn parse(input: &mut ForwardByteParser<'a>) -> Result<Self> {
+        let start = input.remaining();
+        Self::parse_section(input).map_err(|e| e.in_section(Section::SequencesHeader).at(start))
+    }
+
+    /// Parse the sequences section, locating the errors of the FSE tables
+    fn parse_section(input: &mut ForwardByteParser<'a>) -> Result<Self> {
         let num_seq = Self::parse_num_sequences(input)?;
 
         let mut seq = Sequences {
@@ -95,7 +114,7 @@ impl<'a> Sequences<'a> {
 
         // reserved field, must be 0
         if parser.take(2).unwrap() != 0 {
-            return Err(Error::ReservedSet);
+            return Err(Error::ReservedSet.into());
         }
 
         let mut modes_tmp = [
@@ -127,10 +146,20 @@ impl<'a> Sequences<'a> {
                     }
                     SymbolCompressionModeTmp::FseCompressedMode => {
                         new_data = input.slice(input.len())?;
-                        let mut parser = ForwardBitParser::new(new_data)?;
-                        let mode =
-                            SymbolCompressionMode::FseCompressedMode(FseTable::parse(&mut parser)?);
-                        new_data = &new_data[parser.bytes_read()..];
+                        let table = ForwardBitParser::new(new_data)
+                            .map_err(decoders::Error::from)
+                            .and_then(|mut parser| {
+                                let table = FseTable::parse(&mut parser)?;
+                                Ok((table, parser.bytes_read()))
+                            })
+                            .map_err(|e| {
+                                crate::Error::from(e)
+                                    .in_section(Section::FseTable)
+                                    .at(new_data)
+                            });
+                        let (table, size) = table?;
+                        let mode = SymbolCompressionMode::FseCompressedMode(table);
+                        new_data = &new_data[size..];
                         *input = ForwardByteParser::new(new_data);
 
                         Ok(mode)
@@ -142,98 +171,147 @@ impl<'a> Sequences<'a> {
         Ok(res)
     }
 
-    /// Get the correct decoder given the context (which the stores the previous usable decoder)
-    /// the `SymbolCompressionMode` and the code type
-    fn get_decoder(
-        code_type: CodeType,
+    /// Mode giving the table for this block: the mode of the previous block, taken out of
+    /// the context, for the repeat mode, `mode` otherwise
+    fn select_mode(
         mode: SymbolCompressionMode,
-        previous_decoder: &Option<SymbolCompressionMode>,
-    ) -> Result<(Box<dyn BitDecoder<u16>>, SymbolCompressionMode)>
-    where
-        RLEDecoder: BitDecoder<u16>,
-        FseDecoder: BitDecoder<u16>,
-    {
+        previous_mode: &mut Option<SymbolCompressionMode>,
+    ) -> Result<SymbolCompressionMode> {
         match mode {
-            SymbolCompressionMode::RLEMode(b) => Ok((
-                Box::new(RLEDecoder::new(b)),
-                SymbolCompressionMode::RLEMode(b),
-            )),
-            SymbolCompressionMode::FseCompressedMode(table) => Ok((
-                Box::new(FseDecoder::new_from_table(table.clone())),
-                SymbolCompressionMode::FseCompressedMode(table),
-            )),
-            SymbolCompressionMode::RepeatMode => match previous_decoder {
-                None => Err(Error::NoPreviousDecoder),
-                Some(d) if matches!(d, &SymbolCompressionMode::RepeatMode) => {
-                    Err(Error::NoPreviousDecoder) // We don't want infinit recursion
-                }
-                Some(d) => Self::get_decoder(code_type, d.clone(), &None),
-            },
+            SymbolCompressionMode::RepeatMode => previous_mode.take().ok_or_else(|| {
+                crate::Error::from(Error::NoPreviousDecoder).in_section(Section::FseTable)
+            }),
+            mode => Ok(mode),
+        }
+    }
+
+    /// Decoder of `code_type` borrowing the table of `mode`, a mode given by `select_mode`
+    fn get_decoder<'t>(code_type: &CodeType, mode: &'t SymbolCompressionMode) -> CodeDecoder<'t> {
+        match mode {
+            SymbolCompressionMode::RLEMode(b) => CodeDecoder::Rle(RLEDecoder::new(*b)),
+            SymbolCompressionMode::FseCompressedMode(table) => {
+                CodeDecoder::Fse(FseDecoder::new_from_table(table))
+            }
             SymbolCompressionMode::PredefinedMode => {
-                let table = match code_type {
-                    CodeType::LiteralsLength => {
-                        FseTable::from_distribution(6, &LITERALS_LENGTH_DISTRI)?
-                    }
-                    CodeType::Offset => FseTable::from_distribution(5, &OFFSET_DISTRI)?,
-                    CodeType::MatchLength => FseTable::from_distribution(6, &MATCH_LENGTH_DISTRI)?,
-                };
-
-                Ok((
-                    Box::new(FseDecoder::new_from_table(table)),
-                    SymbolCompressionMode::PredefinedMode,
-                ))
+                CodeDecoder::Fse(FseDecoder::new_from_states(predefined_states(code_type)))
             }
+            SymbolCompressionMode::RepeatMode => unreachable!("resolved by select_mode"),
         }
     }
 
     /// Return vector of (literals length, offset value, match length) and update the
     /// decoding context with the tables if appropriate.
     pub fn decode(self, context: &mut DecodingContext) -> Result<Vec<(usize, usize, usize)>> {
-        let (mut ll_decoder, new_ll_repeat) = Self::get_decoder(
-            CodeType::LiteralsLength,
-            self.literal_lengths_mode,
-            &context.ll_repeat_decoder,
-        )?;
-        let (mut offset_decoder, new_cmov_repeat) = Self::get_decoder(
-            CodeType::Offset,
-            self.offsets_mode,
-            &context.cmov_repeat_decoder,
-        )?;
-        let (mut match_decoder, new_ml_repeat) = Self::get_decoder(
-            CodeType::MatchLength,
-            self.match_lengths_mode,
-            &context.ml_repeat_decoder,
-        )?;
+        let mut res = Vec::new();
+        self.decode_into(context, &mut res)?;
 
-        let mut seq_decoder =
-            SequenceDecoder::new(&mut *ll_decoder, &mut *offset_decoder, &mut *match_decoder);
+        Ok(res)
+    }
 
-        let mut parser = BackwardBitParser::new(self.bitstream)?;
-        seq_decoder.initialize(&mut parser)?;
+    /// Decode the sequences into `res`, replacing its content, and update the decoding
+    /// context with the tables if appropriate.
+    pub fn decode_into(
+        self,
+        context: &mut DecodingContext,
+        res: &mut Vec<(usize, usize, usize)>,
+    ) -> Result<()> {
+        res.clear();
+        res.reserve(self.number_of_sequences);
 
-        let mut res = Vec::new();
+        self.for_each(context, |_, sequence| {
+            res.push(sequence);
+            Ok(())
+        })
+    }
 
-        // TODO: verify that -1
-        for _i in 0..self.number_of_sequences - 1 {
-            seq_decoder.update_symbol_value(&mut parser)?;
-            let seq_tmp = seq_decoder.symbol();
-            // println!(
-            //     "{: >2} {: >2} {: >3} {: >2}",
-            //     i, seq_tmp.0, seq_tmp.2, seq_tmp.1,
-            // );
-            res.push(seq_tmp);
-            seq_decoder.update_bits(&mut parser)?;
+    /// Execute each sequence as soon as it is decoded, appending the block content to
+    /// `context.decoded`, and update the decoding context with the tables if appropriate.
+    pub fn execute(self, context: &mut DecodingContext, mut literals: &[u8]) -> Result<()> {
+        let start = context.decoded.len();
+
+        self.for_each(
+            context,
+            |context, (literal_length, offset, match_length)| {
+                Ok(context.execute_sequence(
+                    start,
+                    literal_length,
+                    offset,
+                    match_length,
+                    &mut literals,
+                )?)
+            },
+        )?;
+        context.decoded.check_space(literals.len())?;
+        context.decoded.extend_from_slice(literals);
+
+        Ok(())
+    }
+
+    /// Decode the sequences and give them to `f` one by one, in order
+    fn for_each<F>(self, context: &mut DecodingContext, f: F) -> Result<()>
+    where
+        F: FnMut(&mut DecodingContext, (usize, usize, usize)) -> Result<()>,
+    {
+        // Without sequences, the compression modes are not given and the repeat decoders
+        // are kept as is
+        if self.number_of_sequences == 0 {
+            return Ok(());
         }
 
-        seq_decoder.update_symbol_value(&mut parser)?;
-        res.push(seq_decoder.symbol());
+        // The tables are moved out of the context while the decoders borrow them, and kept
+        // in it for the next blocks
+        let ll_mode = Self::select_mode(self.literal_lengths_mode, &mut context.ll_repeat_decoder)?;
+        let cmov_mode = Self::select_mode(self.offsets_mode, &mut context.cmov_repeat_decoder)?;
+        let ml_mode = Self::select_mode(self.match_lengths_mode, &mut context.ml_repeat_decoder)?;
 
-        // Update the repeat decoder for each type
-        context.cmov_repeat_decoder = Some(new_cmov_repeat);
-        context.ll_repeat_decoder = Some(new_ll_repeat);
-        context.ml_repeat_decoder = Some(new_ml_repeat);
+        let mut seq_decoder = SequenceDecoder::new(
+            Self::get_decoder(&CodeType::LiteralsLength, &ll_mode),
+            Self::get_decoder(&CodeType::Offset, &cmov_mode),
+            Self::get_decoder(&CodeType::MatchLength, &ml_mode),
+        );
 
-        Ok(res)
+        let locate = |e: crate::Error| e.in_section(Section::SequenceBitstream).at(self.bitstream);
+        let mut parser = BackwardBitParser::new(self.bitstream).map_err(|e| locate(e.into()))?;
+        let bits = parser.len();
+        Self::decode_bitstream(
+            self.number_of_sequences,
+            &mut seq_decoder,
+            &mut parser,
+            context,
+            f,
+        )
+        .map_err(|e| locate(e).at_bit((bits - parser.len()) as u64))?;
+
+        context.cmov_repeat_decoder = Some(cmov_mode);
+        context.ll_repeat_decoder = Some(ll_mode);
+        context.ml_repeat_decoder = Some(ml_mode);
+
+        Ok(())
+    }
+
+    /// Decode the `number_of_sequences` sequences of `parser` with `seq_decoder` and
+    /// give them to `f`
+    fn decode_bitstream<F>(
+        number_of_sequences: usize,
+        seq_decoder: &mut SequenceDecoder,
+        parser: &mut BackwardBitParser,
+        context: &mut DecodingContext,
+        mut f: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut DecodingContext, (usize, usize, usize)) -> Result<()>,
+    {
+        seq_decoder.initialize(parser)?;
+
+        // The last sequence does not update the states
+        for _i in 0..number_of_sequences - 1 {
+            seq_decoder.update_symbol_value(parser)?;
+            f(context, seq_decoder.symbol())?;
+            seq_decoder.update_bits(parser)?;
+        }
+
+        seq_decoder.update_symbol_value(parser)?;
+        f(context, seq_decoder.symbol())
     }
 }
 
@@ -259,3 +337,20 @@ pub enum SymbolCompressionMode {
     FseCompressedMode(FseTable),
     RepeatMode,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn predefined_states_match_tables() {
+        for (code_type, accuracy_log, distribution) in [
+            (CodeType::LiteralsLength, 6, &LITERALS_LENGTH_DISTRI[..]),
+            (CodeType::Offset, 5, &OFFSET_DISTRI[..]),
+            (CodeType::MatchLength, 6, &MATCH_LENGTH_DISTRI[..]),
+        ] {
+            let table = FseTable::from_distribution(accuracy_log, distribution).unwrap();
+            assert_eq!(table.table, predefined_states(&code_type));
+        }
+    }
+}
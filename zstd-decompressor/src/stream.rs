@@ -0,0 +1,408 @@
+use std::{io::Write, mem};
+
+use crate::{
+    block::Block,
+    decoding_context::DecodingContext,
+    dictionary::Dictionary,
+    frame::{self, ChecksumPolicy, Header, MAGIC_SKIP, MAGIC_ZSTD},
+    options::DecoderOptions,
+    parsing::{self, ForwardByteParser},
+    ErrorKind,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error{"The decoder failed on a previous chunk ({0:?} error) and cannot be fed anymore"}]
+    Failed(ErrorKind),
+}
+
+type Result<T> = core::result::Result<T, crate::Error>;
+
+/// Part of the input expected next
+enum State {
+    /// Magic number of the next frame
+    Magic,
+    /// Size of a skippable frame
+    SkippableSize,
+    /// Content of a skippable frame, passed through without being buffered
+    Skippable {
+        remaining: usize,
+    },
+    FrameHeader,
+    /// Next block of a ZStandard frame, header included
+    Block {
+        header: Header,
+        /// Number of blocks decoded so far in this frame
+        blocks: usize,
+    },
+    Checksum,
+    /// An error was returned, with this kind
+    Failed(ErrorKind),
+}
+
+/// Result of a call to `StreamDecoder::feed`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of bytes written to the output
+    pub written: u64,
+    /// Number of bytes of the input consumed, all of them unless `feed_bounded` stopped
+    /// early
+    pub consumed: usize,
+    /// At least one frame was finished
+    pub frame_finished: bool,
+    /// The input ends inside a frame, more input is needed to finish it
+    pub needs_more_input: bool,
+}
+
+/// Streaming decoder to which the compressed input is pushed, in chunks of any size.
+///
+/// Frame headers, block headers and blocks split across chunks are kept until the
+/// remaining bytes are fed. Blocks which fit entirely in a chunk are decoded in place.
+/// Only the window of the current frame and an incomplete block are kept in memory. Once
+/// an error is returned, `feed` and `finish` fail with an error of the same kind.
+pub struct StreamDecoder {
+    state: State,
+    context: DecodingContext<'static>,
+    /// Start of the next part of the input, received in previous chunks
+    pending: Vec<u8>,
+    /// Number of frames started so far, skippable frames included
+    frames: usize,
+    /// Offset in the whole input of the start of `pending`, or of the next chunk
+    position: u64,
+    /// Number of bytes output so far
+    output: u64,
+    options: DecoderOptions,
+    dictionary: Option<Dictionary>,
+    checksum_policy: ChecksumPolicy,
+}
+
+impl Default for StreamDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamDecoder {
+    pub fn new() -> Self {
+        StreamDecoder {
+            state: State::Magic,
+            context: DecodingContext::default(),
+            pending: Vec::new(),
+            frames: 0,
+            position: 0,
+            output: 0,
+            options: DecoderOptions::default(),
+            dictionary: None,
+            checksum_policy: ChecksumPolicy::default(),
+        }
+    }
+
+    /// Set the limits of the decoder
+    pub fn with_options(mut self, options: DecoderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Decode frames starting from `dictionary`
+    pub fn with_dictionary(mut self, dictionary: Dictionary) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    /// Set what to do when the content checksum of a frame does not match
+    pub fn with_checksum_policy(mut self, policy: ChecksumPolicy) -> Self {
+        self.checksum_policy = policy;
+        self
+    }
+
+    /// Decode as much of `input` as possible, following the previous chunks, and write
+    /// the decoded content to `out`. The whole input is consumed, incomplete parts of
+    /// the frames are kept until the next call.
+    pub fn feed<W: Write>(&mut self, input: &[u8], out: &mut W) -> Result<Progress> {
+        self.feed_bounded(input, out, u64::MAX)
+    }
+
+    /// Like `feed`, but stop decoding once `max_output` bytes were written, so at most
+    /// one more block than requested is decoded. `Progress::consumed` tells how much of
+    /// `input` was consumed, the rest has to be fed again.
+    pub fn feed_bounded<W: Write>(
+        &mut self,
+        mut input: &[u8],
+        out: &mut W,
+        max_output: u64,
+    ) -> Result<Progress> {
+        if let State::Failed(kind) = self.state {
+            return Err(Error::Failed(kind).into());
+        }
+
+        let mut progress = Progress::default();
+        // Taken to borrow the buffered bytes while the state is updated
+        let mut pending = mem::take(&mut self.pending);
+        let size = input.len();
+
+        let result = loop {
+            if let State::Skippable { remaining } = &mut self.state {
+                if *remaining == 0 {
+                    self.state = State::Magic;
+                    progress.frame_finished = true;
+                    continue;
+                }
+                if input.is_empty() || progress.written >= max_output {
+                    break Ok(());
+                }
+
+                let mut len = (*remaining).min(input.len());
+                if self.options.include_skippable {
+                    let allowed = max_output - progress.written;
+                    len = len.min(usize::try_from(allowed).unwrap_or(usize::MAX));
+                }
+                let (content, rest) = input.split_at(len);
+                *remaining -= content.len();
+                input = rest;
+                self.position += content.len() as u64;
+                if self.options.include_skippable {
+                    if let Err(e) = self.write(content, out, &mut progress) {
+                        break Err(e);
+                    }
+                }
+                continue;
+            }
+
+            if progress.written >= max_output {
+                break Ok(());
+            }
+
+            let needed = match self.needed(&pending, input) {
+                Ok(Some(needed)) => needed,
+                Ok(None) => {
+                    pending.extend_from_slice(input);
+                    input = &[];
+                    break Ok(());
+                }
+                Err(e) => break Err(e),
+            };
+
+            // Decode in place unless the start of the part was received before
+            let data = if pending.is_empty() && input.len() >= needed {
+                let (data, rest) = input.split_at(needed);
+                input = rest;
+                data
+            } else {
+                let missing = needed - pending.len();
+                if input.len() < missing {
+                    pending.extend_from_slice(input);
+                    input = &[];
+                    break Ok(());
+                }
+                pending.extend_from_slice(&input[..missing]);
+                input = &input[missing..];
+                &pending
+            };
+
+            let result = self.process(data, out, &mut progress);
+            self.position += needed as u64;
+            pending.clear();
+            if let Err(e) = result {
+                break Err(e);
+            }
+        };
+
+        self.pending = pending;
+        if let Err(e) = &result {
+            self.state = State::Failed(e.kind());
+            self.pending.clear();
+        }
+        progress.consumed = size - input.len();
+        progress.needs_more_input = self.needs_more_input();
+        result.map(|()| progress)
+    }
+
+    /// Check that the input ended at a frame boundary, after the last chunk was fed
+    pub fn finish(&self) -> Result<()> {
+        if let State::Failed(kind) = self.state {
+            return Err(Error::Failed(kind).into());
+        }
+        if !self.needs_more_input() {
+            return Ok(());
+        }
+
+        let requested = match &self.state {
+            State::Skippable { remaining } => *remaining,
+            _ => self
+                .needed(&self.pending, &[])
+                .ok()
+                .flatten()
+                .map_or(1, |needed| needed - self.pending.len()),
+        };
+        let error = crate::Error::from(parsing::Error::NotEnoughBytes {
+            requested,
+            available: 0,
+        });
+        Err(self.locate(error, &self.pending, self.position))
+    }
+
+    /// Whether the input received so far ends inside a frame
+    fn needs_more_input(&self) -> bool {
+        !matches!(self.state, State::Magic) || !self.pending.is_empty()
+    }
+
+    /// Number of bytes of the next part of the input, or `None` if more bytes are needed
+    /// to know it
+    fn needed(&self, pending: &[u8], input: &[u8]) -> Result<Option<usize>> {
+        Ok(match &self.state {
+            State::Magic | State::SkippableSize | State::Checksum => Some(4),
+            State::FrameHeader => peek(pending, input).map(|[descriptor]| Header::size(descriptor)),
+            State::Block { header, blocks } => match peek::<3>(pending, input) {
+                Some(start) => {
+                    let locate = |e: crate::Error| {
+                        let data = if pending.is_empty() { input } else { pending };
+                        self.locate(e.in_block(*blocks), data, self.position)
+                    };
+                    let (_, block_type, block_size) =
+                        Block::parse_header(&mut ForwardByteParser::new(&start)).map_err(locate)?;
+                    let size =
+                        Block::content_size(block_type, block_size, header.block_maximum_size())
+                            .map_err(locate)?;
+                    Some(3 + size)
+                }
+                None => None,
+            },
+            State::Skippable { .. } | State::Failed(_) => None,
+        })
+    }
+
+    /// Decode the next part of the input, `data`, and update the state
+    fn process<W: Write>(
+        &mut self,
+        data: &[u8],
+        out: &mut W,
+        progress: &mut Progress,
+    ) -> Result<()> {
+        let position = self.position;
+        // Every part leads to a new state, set back below
+        let result = match mem::replace(&mut self.state, State::Magic) {
+            State::Magic => self.start_frame(data),
+            State::SkippableSize => {
+                let size = u32::from_le_bytes(data.try_into().unwrap());
+                self.state = State::Skippable {
+                    remaining: size as usize,
+                };
+                Ok(())
+            }
+            State::FrameHeader => self.parse_header(data),
+            State::Block { header, blocks } => {
+                let result = self.decode_block(data, &header, blocks, out, progress);
+                match result {
+                    Ok(true) if header.content_checksum_flag => self.state = State::Checksum,
+                    Ok(true) => progress.frame_finished = true,
+                    _ => {
+                        self.state = State::Block {
+                            header,
+                            blocks: blocks + 1,
+                        }
+                    }
+                }
+                result.map(|_| ()).map_err(|e| e.in_block(blocks))
+            }
+            State::Checksum => {
+                let checksum = u32::from_le_bytes(data.try_into().unwrap());
+                progress.frame_finished = true;
+                self.checksum_policy.check(&self.context, checksum)
+            }
+            State::Skippable { .. } => unreachable!("skippable content is not buffered"),
+            State::Failed(_) => unreachable!("failed decoders are not fed"),
+        };
+
+        result.map_err(|e| self.locate(e, data, position))
+    }
+
+    /// Start a frame given its magic number
+    fn start_frame(&mut self, magic: &[u8]) -> Result<()> {
+        self.frames += 1;
+        if let Some(max) = self.options.max_frames.filter(|&max| self.frames > max) {
+            return Err(frame::Error::TooManyFrames(max).into());
+        }
+
+        self.state = match u32::from_le_bytes(magic.try_into().unwrap()) {
+            MAGIC_ZSTD => State::FrameHeader,
+            v if v ^ MAGIC_SKIP <= 0x0F => State::SkippableSize,
+            magic => return Err(frame::Error::UnrecognizedMagic(magic).into()),
+        };
+
+        Ok(())
+    }
+
+    fn parse_header(&mut self, data: &[u8]) -> Result<()> {
+        let header = Header::parse(&mut ForwardByteParser::new(data))?;
+        header.reset_context(&mut self.context, self.dictionary.as_ref(), &self.options)?;
+        self.context.checksum = self.checksum_policy.hasher(&header);
+        self.state = State::Block { header, blocks: 0 };
+
+        Ok(())
+    }
+
+    /// Decode the block `index` of the frame and write its content to `out`, return
+    /// whether it is the last block of the frame
+    fn decode_block<W: Write>(
+        &mut self,
+        data: &[u8],
+        header: &Header,
+        index: usize,
+        out: &mut W,
+        progress: &mut Progress,
+    ) -> Result<bool> {
+        if let Some(max) = self.options.max_blocks.filter(|&max| index >= max) {
+            return Err(frame::Error::TooManyBlocks(max).into());
+        }
+
+        let (block, last) = Block::parse_with_max_size(
+            &mut ForwardByteParser::new(data),
+            header.block_maximum_size(),
+        )?;
+        block.decode(&mut self.context)?;
+        header.check_content_size(self.context.produced, last)?;
+
+        let size = self.context.decoded.len() as u64;
+        self.count_output(size)?;
+        self.context.flush(out)?;
+        progress.written += size;
+
+        Ok(last)
+    }
+
+    /// Write the content of a skippable frame to `out`
+    fn write<W: Write>(&mut self, data: &[u8], out: &mut W, progress: &mut Progress) -> Result<()> {
+        self.count_output(data.len() as u64)?;
+        out.write_all(data)?;
+        progress.written += data.len() as u64;
+
+        Ok(())
+    }
+
+    /// Count `size` more bytes of output, enforcing the maximum output size
+    fn count_output(&mut self, size: u64) -> Result<()> {
+        self.output += size;
+        Ok(self.options.check_output(self.output)?)
+    }
+
+    /// Locate an error of the current frame, happening in `data` which starts at
+    /// `position` in the whole input
+    fn locate(&self, error: crate::Error, data: &[u8], position: u64) -> crate::Error {
+        let error = match self.frames {
+            0 => error,
+            frames => error.in_frame(frames - 1),
+        };
+        error.at(data).relative_to(data, position)
+    }
+}
+
+/// First `N` bytes of `pending` followed by `input`, if there are enough
+fn peek<const N: usize>(pending: &[u8], input: &[u8]) -> Option<[u8; N]> {
+    let mut start = [0; N];
+    let mut bytes = pending.iter().chain(input);
+    for byte in &mut start {
+        *byte = *bytes.next()?;
+    }
+
+    Some(start)
+}
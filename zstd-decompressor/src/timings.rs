@@ -0,0 +1,37 @@
+use std::{ops::AddAssign, time::Duration};
+
+/// Time spent in each phase of the decoding, measured by a `DecodingContext` whose
+/// `timings` are set.
+///
+/// Sequences are decoded then executed separately while they are measured, instead of
+/// being executed as soon as they are decoded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Timings {
+    /// Parsing of the frames and of their blocks, measured by the caller
+    pub frame_parsing: Duration,
+    pub literals: Duration,
+    pub sequences_decoding: Duration,
+    pub sequences_execution: Duration,
+    pub checksum: Duration,
+}
+
+impl Timings {
+    /// Time spent in all the phases
+    pub fn total(&self) -> Duration {
+        self.frame_parsing
+            + self.literals
+            + self.sequences_decoding
+            + self.sequences_execution
+            + self.checksum
+    }
+}
+
+impl AddAssign for Timings {
+    fn add_assign(&mut self, other: Self) {
+        self.frame_parsing += other.frame_parsing;
+        self.literals += other.literals;
+        self.sequences_decoding += other.sequences_decoding;
+        self.sequences_execution += other.sequences_execution;
+        self.checksum += other.checksum;
+    }
+}
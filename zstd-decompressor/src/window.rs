@@ -0,0 +1,164 @@
+use crate::output::Output;
+use alloc::vec::Vec;
+
+/// Ring buffer keeping the last `capacity` bytes pushed into it. The memory is only
+/// allocated as the window fills up.
+#[derive(Debug, Default)]
+pub struct Window {
+    buffer: Vec<u8>,
+    capacity: usize,
+    /// Position right after the most recent byte once the buffer is full
+    end: usize,
+}
+
+impl Window {
+    pub fn new(capacity: usize) -> Self {
+        Window {
+            buffer: Vec::new(),
+            capacity,
+            end: 0,
+        }
+    }
+
+    /// Maximum number of bytes kept in the window
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of bytes currently available in the window
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Append `data` to the window, discarding the oldest bytes if it is full
+    pub fn push(&mut self, data: &[u8]) {
+        let mut data = &data[data.len().saturating_sub(self.capacity)..];
+
+        if self.buffer.len() < self.capacity {
+            let len = (self.capacity - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..len]);
+            data = &data[len..];
+            self.end = self.buffer.len() % self.capacity;
+        }
+
+        while !data.is_empty() {
+            let len = (self.capacity - self.end).min(data.len());
+            self.buffer[self.end..self.end + len].copy_from_slice(&data[..len]);
+            self.end = (self.end + len) % self.capacity;
+            data = &data[len..];
+        }
+    }
+
+    /// Return the byte located `distance` bytes before the end of the window,
+    /// 1 being the most recent byte
+    pub fn get(&self, distance: usize) -> Option<u8> {
+        if distance == 0 || distance > self.len() {
+            return None;
+        }
+
+        Some(self.buffer[(self.end + self.len() - distance) % self.len()])
+    }
+
+    /// Append to `output` at most `len` bytes starting `distance` bytes before the end
+    /// of the window, stopping at the most recent byte. Return the number of bytes copied.
+    pub fn copy_to(&self, distance: usize, len: usize, output: &mut Output) -> usize {
+        if distance == 0 || distance > self.len() {
+            return 0;
+        }
+
+        let len = len.min(distance);
+        let start = (self.end + self.len() - distance) % self.len();
+        let first = len.min(self.len() - start);
+        output.extend_from_slice(&self.buffer[start..start + first]);
+        output.extend_from_slice(&self.buffer[..len - first]);
+
+        len
+    }
+
+    /// Forget the content of the window, keeping its allocation
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.end = 0;
+    }
+
+    /// Forget the content of the window and change its capacity, keeping its allocation
+    pub fn reset(&mut self, capacity: usize) {
+        self.clear();
+        self.capacity = capacity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use super::Window;
+    use crate::output::Output;
+
+    #[test]
+    fn push_without_wrapping() {
+        let mut window = Window::new(8);
+        window.push(&[1, 2, 3]);
+
+        assert_eq!(3, window.len());
+        assert_eq!(Some(3), window.get(1));
+        assert_eq!(Some(1), window.get(3));
+        assert_eq!(None, window.get(4));
+    }
+
+    #[test]
+    fn push_wraps_around() {
+        let mut window = Window::new(4);
+        window.push(&[1, 2, 3]);
+        window.push(&[4, 5, 6]);
+
+        assert_eq!(4, window.len());
+        assert_eq!(
+            vec![3, 4, 5, 6],
+            (1..=4)
+                .rev()
+                .map(|d| window.get(d).unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn push_more_than_capacity() {
+        let mut window = Window::new(3);
+        window.push(&[1]);
+        window.push(&[2, 3, 4, 5, 6, 7]);
+
+        assert_eq!(Some(5), window.get(3));
+        assert_eq!(Some(7), window.get(1));
+    }
+
+    #[test]
+    fn copy_to_across_the_end_of_the_buffer() {
+        let mut window = Window::new(4);
+        window.push(&[1, 2, 3, 4, 5, 6]);
+
+        let mut output = Output::default();
+        assert_eq!(3, window.copy_to(4, 3, &mut output));
+        assert_eq!(vec![3, 4, 5], output[..]);
+
+        // Stops at the most recent byte
+        assert_eq!(2, window.copy_to(2, 10, &mut output));
+        assert_eq!(vec![3, 4, 5, 5, 6], output[..]);
+    }
+
+    #[test]
+    fn reset_changes_capacity() {
+        let mut window = Window::new(4);
+        window.push(&[1, 2, 3, 4, 5, 6]);
+        window.reset(2);
+
+        assert!(window.is_empty());
+        window.push(&[7, 8, 9]);
+        assert_eq!(2, window.len());
+        assert_eq!(Some(8), window.get(2));
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod allocations_tests {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+    };
+
+    use zstd_decompressor::{
+        decoders::huffman::HuffmanDecoder, decoding_context::DecodingContext, frame,
+        literals::LiteralsSection,
+    };
+
+    /// System allocator counting the allocations of each thread, so that the tests running
+    /// in parallel do not interfere
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Number of allocations made by `f` on the current thread
+    fn allocations(f: impl FnOnce()) -> usize {
+        let start = ALLOCATIONS.with(Cell::get);
+        f();
+        ALLOCATIONS.with(Cell::get) - start
+    }
+
+    /// Streams decoding to "BABCBB" with `huffman_decoder`
+    const STREAM: [u8; 2] = [0x97, 0x01];
+    const STREAMS: [u8; 8] = [0x97, 0x01, 0x97, 0x01, 0x97, 0x01, 0x97, 0x01];
+
+    fn huffman_decoder() -> HuffmanDecoder {
+        let weights: Vec<_> = std::iter::repeat_n(0, 65).chain([1, 2]).collect();
+        HuffmanDecoder::from_weights(weights).unwrap()
+    }
+
+    /// Decode a treeless literals section with the Huffman tree of the context
+    fn decode_treeless(regenerated_size: usize, n_streams: u8, data: &[u8]) -> usize {
+        let mut context = DecodingContext::new(frame::MAX_WIN_SIZE).unwrap();
+        context.huffman_decoder = Some(huffman_decoder());
+        let mut res = Vec::with_capacity(regenerated_size);
+        let jump_table = match n_streams {
+            1 => [data.len(), 0, 0, 0],
+            _ => [data.len() / 4; 4],
+        };
+
+        let count = allocations(|| {
+            LiteralsSection::CompressedLiteralsBlock {
+                huffman_decoder: None,
+                regenerated_size,
+                n_streams,
+                jump_table,
+                data,
+            }
+            .decode_into(&mut context, &mut res)
+            .unwrap()
+        });
+        assert_eq!(b"BABCBB".repeat(n_streams as usize), res);
+
+        count
+    }
+
+    #[test]
+    fn single_stream_no_allocation() {
+        assert_eq!(0, decode_treeless(6, 1, &STREAM));
+    }
+
+    #[test]
+    fn four_streams_no_allocation() {
+        assert_eq!(0, decode_treeless(24, 4, &STREAMS));
+    }
+}
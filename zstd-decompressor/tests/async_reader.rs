@@ -0,0 +1,55 @@
+#[cfg(all(test, feature = "async"))]
+mod async_decoder_tests {
+    use std::io::{self, Read};
+
+    use tokio::io::{AsyncReadExt, BufReader};
+    use zstd_decompressor::{
+        async_reader::AsyncZstdDecoder, dictionary::Dictionary, reader::ZstdReader, Error,
+        ErrorKind,
+    };
+
+    const ROMEO: &[u8] = include_bytes!("../../resources/romeo3.txt.zst");
+    const MOBY_DICK: &[u8] = include_bytes!("../../resources/moby-dick.txt.zst");
+    const SKIPPABLES: &[u8] = include_bytes!("../../resources/skippables.zst");
+    const DICTIONARY: &[u8] = include_bytes!("../../resources/moby-dick.dict");
+    const SAMPLE: &[u8] = include_bytes!("../../resources/moby-dick-sample.txt.zst");
+    const SAMPLE_DECOMPRESSED: &[u8] = include_bytes!("../../resources/moby-dick-sample.txt");
+
+    fn read(data: &[u8]) -> Vec<u8> {
+        let mut res = vec![];
+        ZstdReader::new(data).read_to_end(&mut res).unwrap();
+        res
+    }
+
+    #[tokio::test]
+    async fn read_ok() {
+        let input = [ROMEO, SKIPPABLES, MOBY_DICK].concat();
+        let expected = read(&input);
+
+        for capacity in [1, 10, 8192, input.len()] {
+            let mut decoder = AsyncZstdDecoder::new(BufReader::with_capacity(capacity, &input[..]));
+            let mut output = vec![];
+            decoder.read_to_end(&mut output).await.unwrap();
+            assert_eq!(expected, output);
+        }
+    }
+
+    #[tokio::test]
+    async fn read_with_dictionary_ok() {
+        let mut decoder =
+            AsyncZstdDecoder::new(SAMPLE).with_dictionary(Dictionary::parse(DICTIONARY).unwrap());
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).await.unwrap();
+        assert_eq!(SAMPLE_DECOMPRESSED, output);
+    }
+
+    #[tokio::test]
+    async fn truncated_input_error() {
+        let mut decoder = AsyncZstdDecoder::new(&MOBY_DICK[..MOBY_DICK.len() / 2]);
+        let error = decoder.read_to_end(&mut vec![]).await.unwrap_err();
+
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        let error = error.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert_eq!(ErrorKind::UnexpectedEnd, error.kind());
+    }
+}
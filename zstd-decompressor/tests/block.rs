@@ -4,8 +4,9 @@ mod block_tests {
     use zstd_decompressor::decoding_context::DecodingContext;
     use zstd_decompressor::frame;
     use zstd_decompressor::parsing::{self, ForwardByteParser};
+    use zstd_decompressor::Section;
 
-    fn get_context() -> DecodingContext {
+    fn get_context() -> DecodingContext<'static> {
         DecodingContext::new(frame::MAX_WIN_SIZE).unwrap()
     }
 
@@ -22,16 +23,16 @@ mod block_tests {
         assert!(matches!(block, Block::RawBlock(&[0x10, 0x20, 0x30, 0x40])));
         assert_eq!(1, parser.len());
         block.decode(&mut context).unwrap();
-        assert_eq!(vec![0x10, 0x20, 0x30, 0x40], context.decoded);
+        assert_eq!(vec![0x10, 0x20, 0x30, 0x40], context.decoded[..]);
     }
 
     #[test]
     fn decode_rle_block_not_last() {
         let mut context = get_context();
         let mut parser = ForwardByteParser::new(&[
-            // RLE block, not last, byte 0x42 and repeat 0x30004,
+            // RLE block, not last, byte 0x42 and repeat 0x10004,
             // and an extra 0x50 at the end.
-            0x22, 0x0, 0x18, 0x42, 0x50,
+            0x22, 0x0, 0x08, 0x42, 0x50,
         ]);
         let (block, last) = Block::parse(&mut parser).unwrap();
         assert!(!last);
@@ -39,13 +40,13 @@ mod block_tests {
             block,
             Block::RLEBlock {
                 byte: 0x42,
-                repeat: 196612
+                repeat: 65540
             }
         ));
         assert_eq!(1, parser.len());
         block.decode(&mut context).unwrap();
-        assert_eq!(196612, context.decoded.len());
-        assert!(context.decoded.into_iter().all(|b| b == 0x42));
+        assert_eq!(65540, context.decoded.len());
+        assert!(context.decoded.iter().all(|&b| b == 0x42));
     }
 
     #[test]
@@ -57,7 +58,12 @@ mod block_tests {
         ]);
 
         let res = Block::parse(&mut parser);
-        assert!(matches!(res, Err(block::Error::ReservedBlockType())));
+        let error = res.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<block::Error>(),
+            Some(block::Error::ReservedBlockType())
+        ));
+        assert_eq!(Some(Section::BlockHeader), error.section());
     }
 
     #[test]
@@ -70,11 +76,52 @@ mod block_tests {
 
         let res = Block::parse(&mut parser);
         assert!(matches!(
-            res,
-            Err(block::Error::ParsingError(parsing::Error::NotEnoughBytes {
+            res.unwrap_err().downcast_ref::<parsing::Error>(),
+            Some(parsing::Error::NotEnoughBytes {
                 requested: 4,
                 available: 3
-            }))
+            })
+        ));
+    }
+
+    #[test]
+    fn block_larger_than_128kib_error() {
+        let mut parser = ForwardByteParser::new(&[
+            // RLE block, not last, byte 0x42 and repeat 0x30004
+            0x22, 0x0, 0x18, 0x42,
+        ]);
+
+        assert!(matches!(
+            Block::parse(&mut parser)
+                .unwrap_err()
+                .downcast_ref::<block::Error>(),
+            Some(block::Error::BlockSizeTooBig {
+                max: 131072,
+                got: 196612
+            })
+        ));
+    }
+
+    #[test]
+    fn block_larger_than_window_error() {
+        let data = [0x21, 0x0, 0x0, 0x10, 0x20, 0x30, 0x40];
+
+        assert!(matches!(
+            Block::parse_with_max_size(&mut ForwardByteParser::new(&data), 2)
+                .unwrap_err()
+                .downcast_ref::<block::Error>(),
+            Some(block::Error::BlockSizeTooBig { max: 2, got: 4 })
+        ));
+
+        // The decoded size is checked against the window of the context as well
+        let mut context = DecodingContext::new(2).unwrap();
+        let (block, _) = Block::parse(&mut ForwardByteParser::new(&data)).unwrap();
+        assert!(matches!(
+            block
+                .decode(&mut context)
+                .unwrap_err()
+                .downcast_ref::<block::Error>(),
+            Some(block::Error::LargeBlockSize { max: 2, got: 4 })
         ));
     }
 }
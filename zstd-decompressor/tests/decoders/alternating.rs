@@ -1,5 +1,4 @@
 #[cfg(test)]
-
 mod alternating_tests {
 
     use zstd_decompressor::{
@@ -42,11 +41,10 @@ mod alternating_tests {
     #[test]
     fn new_test_ok() {
         let table = get_table();
-        let table_bis = table.clone();
 
-        let alternating = alternating::AlternatingDecoder::new(table);
+        let alternating = alternating::AlternatingDecoder::new(&table);
 
-        let decodeur = FseDecoder::new_from_table(table_bis);
+        let decodeur = FseDecoder::new_from_table(&table);
         let first = alternating.first_decoder;
         let second = alternating.second_decoder;
         assert_eq!(second, decodeur);
@@ -58,7 +56,7 @@ mod alternating_tests {
     fn alternating_initialize_test() {
         let table = get_table();
 
-        let mut alternating = alternating::AlternatingDecoder::new(table);
+        let mut alternating = alternating::AlternatingDecoder::new(&table);
         // let data: &[u8; 3] = &[0b00111111, 0b11000000, 0b1100];
         let data: &[u8; 3] = &[0b1001_1_000, 0b0000_0001, 0b1111_1110];
         let mut parser = BackwardBitParser::new(data).unwrap();
@@ -79,7 +77,7 @@ mod alternating_tests {
     fn ending_get_both_symbols_ok() {
         let table = get_table();
 
-        let mut alternating = alternating::AlternatingDecoder::new(table);
+        let mut alternating = alternating::AlternatingDecoder::new(&table);
         // let data: &[u8; 3] = &[0b0011_1111, 0b1100_0000, 0b1100];
         let data: &[u8; 3] = &[0b1001_1000, 0b0000_0001, 0b1111_1110];
         let mut parser = BackwardBitParser::new(data).unwrap();
@@ -1,7 +1,10 @@
 #[cfg(test)]
 mod fse_decoder_tests {
 
-    use zstd_decompressor::{decoders::fse, parsing::ForwardBitParser};
+    use zstd_decompressor::{
+        decoders::{self, fse},
+        parsing::ForwardBitParser,
+    };
 
     #[test]
     fn parse_fse_table_test_ok() {
@@ -29,6 +32,19 @@ mod fse_decoder_tests {
         ));
     }
 
+    #[test]
+    fn fse_table_from_distribution_corrupted_error() {
+        // Probabilities must add up to the table size
+        assert!(matches!(
+            fse::FseTable::from_distribution(5, &[18, 6, 2, 2, 2, 1]),
+            Err(decoders::Error::CorruptedTable)
+        ));
+        assert!(matches!(
+            fse::FseTable::from_distribution(5, &[18, 6, 2, 2, 2, 1, 1, -2, 2]),
+            Err(decoders::Error::CorruptedTable)
+        ));
+    }
+
     #[test]
     fn fse_table_from_distribution2_ok() {
         let data: Vec<u8> = vec![
@@ -101,7 +117,7 @@ mod fes_decoder_tests {
         let data = &[0b10100000, 0b11110000];
         let mut parser = BackwardBitParser::new(data).unwrap();
 
-        let mut decoder = FseDecoder::new_from_table(table);
+        let mut decoder = FseDecoder::new_from_table(&table);
         decoder.initialize(&mut parser).unwrap();
 
         let good_values = &[0, 0, 1, 0, 3, 1, 0, 3, 0, 1, 3];
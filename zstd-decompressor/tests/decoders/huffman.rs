@@ -1,6 +1,9 @@
 mod huffman_test {
     use zstd_decompressor::{
-        decoders::huffman::{self, HuffmanDecoder},
+        decoders::{
+            self,
+            huffman::{self, HuffmanDecoder},
+        },
         parsing::{BackwardBitParser, ForwardByteParser},
     };
 
@@ -8,52 +11,34 @@ mod huffman_test {
     fn example_tree() {
         let example = huffman::build_example_tree();
         println!("{:?}", example); //should be smething like : HuffmanDecoder { 00: 65, 01: 67, 1: 66 }
-        let widths: Vec<_> = std::iter::repeat(0).take(65).chain([2, 1, 2]).collect();
+        let widths: Vec<_> = std::iter::repeat_n(0, 65).chain([2, 1, 2]).collect();
         println!("{:?}", HuffmanDecoder::from_number_of_bits(widths));
-        let weights: Vec<_> = std::iter::repeat(0).take(65).chain([1, 2]).collect();
+        let weights: Vec<_> = std::iter::repeat_n(0, 65).chain([1, 2]).collect();
         println!("{:?}", HuffmanDecoder::from_weights(weights));
     }
 
     #[test]
-    fn insert_example_tree_ok() {
-        let mut example = HuffmanDecoder::Absent;
-
-        let should_be = HuffmanDecoder::Tree {
-            left: Box::new(HuffmanDecoder::Tree {
-                left: Box::new(HuffmanDecoder::Symbol { payload: b'A' }),
-                right: Box::new(HuffmanDecoder::Absent),
-            }),
-            right: Box::new(HuffmanDecoder::Absent),
-        };
-
-        assert!(example.insert(b'A', 2));
-        assert_eq!(example, should_be);
-
-        let should_be = HuffmanDecoder::Tree {
-            left: Box::new(HuffmanDecoder::Tree {
-                left: Box::new(HuffmanDecoder::Symbol { payload: b'A' }),
-                right: Box::new(HuffmanDecoder::Symbol { payload: b'C' }),
-            }),
-            right: Box::new(HuffmanDecoder::Absent),
-        };
-        assert!(example.insert(b'C', 2));
-        assert_eq!(example, should_be);
+    fn example_tree_decode_ok() {
+        let widths: Vec<_> = std::iter::repeat_n(0, 65).chain([2, 1, 2]).collect();
+        let example = huffman::build_example_tree();
+        assert_eq!(
+            example,
+            HuffmanDecoder::from_number_of_bits(widths).unwrap()
+        );
 
-        let should_be = HuffmanDecoder::Tree {
-            left: Box::new(HuffmanDecoder::Tree {
-                left: Box::new(HuffmanDecoder::Symbol { payload: b'A' }),
-                right: Box::new(HuffmanDecoder::Symbol { payload: b'C' }),
-            }),
-            right: Box::new(HuffmanDecoder::Symbol { payload: b'B' }),
-        };
-        assert!(example.insert(b'B', 1));
-        assert_eq!(example, should_be);
+        // B is 1, A is 00 and C is 01
+        let mut parser = BackwardBitParser::new(&[0x97, 0x01]).unwrap();
+        let mut result = String::new();
+        while !parser.is_empty() {
+            result.push(example.decode(&mut parser).unwrap() as char);
+        }
+        assert_eq!(result, "BABCBB");
     }
 
     #[test]
     fn huffman_project_example() {
         // 0 repeated 65 times, 1, 2
-        let weights: Vec<_> = std::iter::repeat(0).take(65).chain([1, 2]).collect();
+        let weights: Vec<_> = std::iter::repeat_n(0, 65).chain([1, 2]).collect();
         let decoder = HuffmanDecoder::from_weights(weights).unwrap();
         dbg!(&decoder);
         let mut parser = BackwardBitParser::new(&[0x97, 0x01]).unwrap();
@@ -68,8 +53,7 @@ mod huffman_test {
     #[test]
     fn parse_direct_stream_ok() {
         // 0 repeated 65 times, 1, 2 weights encoded with 2 weights per byte
-        let mut weights = std::iter::repeat(0)
-            .take(65)
+        let mut weights = std::iter::repeat_n(0, 65)
             .chain([1, 2])
             .collect::<Vec<u8>>()
             .chunks(2)
@@ -93,4 +77,64 @@ mod huffman_test {
         }
         assert_eq!(result, "BABCBB");
     }
+
+    #[test]
+    fn from_number_of_bits_overlapping_error() {
+        let widths: Vec<_> = std::iter::repeat_n(0, 65).chain([1, 1, 1]).collect();
+        assert!(matches!(
+            HuffmanDecoder::from_number_of_bits(widths),
+            Err(decoders::Error::OverlappingHuffmanCodes)
+        ));
+    }
+
+    #[test]
+    fn from_number_of_bits_too_long_error() {
+        assert!(matches!(
+            HuffmanDecoder::from_number_of_bits(vec![12, 1]),
+            Err(decoders::Error::InvalidHuffmanWeights)
+        ));
+    }
+
+    #[test]
+    fn from_weights_power_of_two_sum_ok() {
+        // The implicit last weight completes the sum to the next power of 2: 1 here
+        assert_eq!(
+            HuffmanDecoder::from_number_of_bits(vec![1, 1]).unwrap(),
+            HuffmanDecoder::from_weights(vec![1]).unwrap()
+        );
+        // Weights 2, 1, 1 sum to 4, the last weight is 3
+        assert_eq!(
+            HuffmanDecoder::from_number_of_bits(vec![2, 3, 3, 1]).unwrap(),
+            HuffmanDecoder::from_weights(vec![2, 1, 1]).unwrap()
+        );
+    }
+
+    #[test]
+    fn from_weights_invalid_error() {
+        // The last weight cannot complete the sum to a power of 2
+        assert!(matches!(
+            HuffmanDecoder::from_weights(vec![2, 1, 1, 1]),
+            Err(decoders::Error::InvalidHuffmanWeights)
+        ));
+        // Weights are at most 11
+        assert!(matches!(
+            HuffmanDecoder::from_weights(vec![12, 1]),
+            Err(decoders::Error::InvalidHuffmanWeights)
+        ));
+        assert!(matches!(
+            HuffmanDecoder::from_weights(vec![]),
+            Err(decoders::Error::InvalidHuffmanWeights)
+        ));
+    }
+
+    #[test]
+    fn decode_incomplete_tree_error() {
+        // Only the prefix 0 is used
+        let tree = HuffmanDecoder::from_number_of_bits(vec![1]).unwrap();
+        let mut parser = BackwardBitParser::new(&[0x03]).unwrap();
+        assert!(matches!(
+            tree.decode(&mut parser),
+            Err(decoders::Error::UnknownHuffmanCode)
+        ));
+    }
 }
@@ -1,6 +1,29 @@
 #[cfg(test)]
 mod sequence_decoer_tests {
-    use zstd_decompressor::parsing::ForwardByteParser;
+    use zstd_decompressor::{
+        decoders::{
+            rle::RLEDecoder,
+            sequence::{CodeDecoder, SequenceDecoder},
+            BitDecoder,
+        },
+        parsing::{BackwardBitParser, ForwardByteParser},
+    };
+
+    #[test]
+    fn rle_codes_ok() {
+        let mut decoder = SequenceDecoder::new(
+            CodeDecoder::Rle(RLEDecoder::new(1)),
+            CodeDecoder::Rle(RLEDecoder::new(2)),
+            CodeDecoder::Rle(RLEDecoder::new(0)),
+        );
+        // 2 extra bits for the offset code
+        let mut parser = BackwardBitParser::new(&[0b0000_0110]).unwrap();
+
+        decoder.initialize(&mut parser).unwrap();
+        decoder.update_symbol_value(&mut parser).unwrap();
+        assert_eq!((1, 6, 3), decoder.symbol());
+        assert!(parser.is_empty());
+    }
 
     #[test]
     fn fuzzer_panic_ok() {
@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod decompressor_tests {
+    use zstd_decompressor::{
+        decompressor::Decompressor, dictionary::Dictionary, options::DecoderOptions,
+        parsing::ForwardByteParser, ErrorKind,
+    };
+
+    const ROMEO: &[u8] = include_bytes!("../../resources/romeo3.txt.zst");
+    /// Frame which does not announce its content size
+    const ROMEO_WLOG10: &[u8] = include_bytes!("../../resources/romeo-wlog10.txt.zst");
+    const MOBY_DICK: &[u8] = include_bytes!("../../resources/moby-dick.txt.zst");
+    const SKIPPABLES: &[u8] = include_bytes!("../../resources/skippables.zst");
+    const DICTIONARY: &[u8] = include_bytes!("../../resources/moby-dick.dict");
+    const SAMPLE: &[u8] = include_bytes!("../../resources/moby-dick-sample.txt.zst");
+    const SAMPLE_DECOMPRESSED: &[u8] = include_bytes!("../../resources/moby-dick-sample.txt");
+    /// Compressed by `zstd -3` with a Huffman tree whose explicit weights sum to a power of 2
+    const HUFFMAN_POW2: &[u8] = include_bytes!("../../resources/huffman-pow2.txt.zst");
+    const HUFFMAN_POW2_DECOMPRESSED: &[u8] = include_bytes!("../../resources/huffman-pow2.txt");
+
+    /// Content of `data`, decoded frame by frame, skippable frames ignored
+    fn read(data: &[u8]) -> Vec<u8> {
+        ForwardByteParser::new(data)
+            .iter_with_options(DecoderOptions::default())
+            .flat_map(|frame| frame.unwrap().decode().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn decompress_ok() {
+        let mut decompressor = Decompressor::new();
+        assert_eq!(read(ROMEO), decompressor.decompress(ROMEO).unwrap());
+    }
+
+    #[test]
+    fn decompress_huffman_weights_power_of_two_ok() {
+        let mut decompressor = Decompressor::new();
+        assert_eq!(
+            HUFFMAN_POW2_DECOMPRESSED,
+            decompressor.decompress(HUFFMAN_POW2).unwrap()
+        );
+    }
+
+    #[test]
+    fn decompressor_reused_across_calls() {
+        let mut decompressor = Decompressor::new();
+        for data in [MOBY_DICK, ROMEO, MOBY_DICK, ROMEO] {
+            assert_eq!(read(data), decompressor.decompress(data).unwrap());
+        }
+    }
+
+    #[test]
+    fn decompressor_reused_after_error() {
+        let mut decompressor = Decompressor::new();
+        assert!(decompressor
+            .decompress(&MOBY_DICK[..MOBY_DICK.len() / 2])
+            .is_err());
+        assert_eq!(read(ROMEO), decompressor.decompress(ROMEO).unwrap());
+    }
+
+    #[test]
+    fn decompress_to_vec_appends() {
+        let mut decompressor = Decompressor::new();
+        let mut output = b"Header".to_vec();
+        let written = decompressor.decompress_to_vec(ROMEO, &mut output).unwrap();
+
+        assert_eq!(read(ROMEO).len(), written);
+        assert!(output.starts_with(b"Header"));
+        assert_eq!(read(ROMEO), output[6..]);
+    }
+
+    #[test]
+    fn decompress_with_dictionary() {
+        let mut decompressor =
+            Decompressor::new().with_dictionary(Dictionary::parse(DICTIONARY).unwrap());
+
+        for _ in 0..2 {
+            assert_eq!(
+                SAMPLE_DECOMPRESSED,
+                decompressor.decompress(SAMPLE).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn decompress_skippable_frames() {
+        let mut decompressor = Decompressor::new();
+        assert!(decompressor.decompress(SKIPPABLES).unwrap().is_empty());
+
+        let mut decompressor =
+            Decompressor::new().with_options(DecoderOptions::new().include_skippable(true));
+        assert_eq!(
+            vec![0x10, 0x20, 0x30, 0x42],
+            decompressor.decompress(SKIPPABLES).unwrap()
+        );
+    }
+
+    #[test]
+    fn decompress_max_output_error() {
+        let mut decompressor =
+            Decompressor::new().with_options(DecoderOptions::new().max_output(100));
+        let error = decompressor.decompress(ROMEO).unwrap_err();
+        assert_eq!(ErrorKind::OutputTooBig, error.kind());
+    }
+
+    #[test]
+    fn decompress_into_ok() {
+        let input = [ROMEO, SKIPPABLES, MOBY_DICK, ROMEO_WLOG10].concat();
+        let expected = read(&input);
+        let mut decompressor = Decompressor::new();
+        let mut dst = vec![0; expected.len() + 10];
+
+        for _ in 0..2 {
+            let written = decompressor.decompress_into(&input, &mut dst).unwrap();
+            assert_eq!(expected.len(), written);
+            assert_eq!(expected, dst[..written]);
+        }
+    }
+
+    #[test]
+    fn decompress_into_with_dictionary() {
+        let mut decompressor =
+            Decompressor::new().with_dictionary(Dictionary::parse(DICTIONARY).unwrap());
+        let mut dst = vec![0; SAMPLE_DECOMPRESSED.len()];
+
+        let written = decompressor.decompress_into(SAMPLE, &mut dst).unwrap();
+        assert_eq!(SAMPLE_DECOMPRESSED, &dst[..written]);
+    }
+
+    #[test]
+    fn decompress_into_dst_too_small_error() {
+        let mut decompressor = Decompressor::new();
+
+        for input in [ROMEO, ROMEO_WLOG10] {
+            let size = read(input).len();
+            let mut dst = vec![0; size - 1];
+            let error = decompressor.decompress_into(input, &mut dst).unwrap_err();
+            assert_eq!(ErrorKind::DstTooSmall, error.kind());
+            // Only the last frame does not fit
+            let frames = ForwardByteParser::new(input).iter().count();
+            assert_eq!(Some(frames - 1), error.frame());
+        }
+
+        // The second frame does not fit
+        let size = read(ROMEO).len();
+        let mut dst = vec![0; 2 * size - 1];
+        let error = decompressor
+            .decompress_into(&[ROMEO, ROMEO].concat(), &mut dst)
+            .unwrap_err();
+        assert_eq!(ErrorKind::DstTooSmall, error.kind());
+        let frames = ForwardByteParser::new(ROMEO).iter().count();
+        assert_eq!(Some(2 * frames - 1), error.frame());
+        assert!(error.offset().unwrap() > ROMEO.len() as u64);
+
+        // The decompressor can still be used
+        assert_eq!(size, decompressor.decompress_into(ROMEO, &mut dst).unwrap());
+    }
+}
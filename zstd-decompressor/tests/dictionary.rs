@@ -0,0 +1,201 @@
+#[cfg(test)]
+mod dictionary_tests {
+    #[cfg(feature = "std")]
+    use std::io::Read;
+
+    #[cfg(feature = "std")]
+    use zstd_decompressor::reader::ZstdReader;
+    use zstd_decompressor::{
+        dictionary::{self, Dictionary},
+        frame::{self, Frame},
+        parsing::ForwardByteParser,
+        ErrorKind, Section,
+    };
+
+    const DICTIONARY: &[u8] = include_bytes!("../../resources/moby-dick.dict");
+    const COMPRESSED: &[u8] = include_bytes!("../../resources/moby-dick-sample.txt.zst");
+    const DECOMPRESSED: &[u8] = include_bytes!("../../resources/moby-dick-sample.txt");
+
+    #[cfg(feature = "std")]
+    const ROMEO: &[u8] = include_bytes!("../../resources/romeo3.txt.zst");
+    /// Compressed with `zstd --patch-from` using the content of `ROMEO`
+    #[cfg(feature = "std")]
+    const PATCH: &[u8] = include_bytes!("../../resources/romeo-patched.txt.zst");
+    #[cfg(feature = "std")]
+    const PATCHED: &[u8] = include_bytes!("../../resources/romeo-patched.txt");
+
+    fn parse_frame() -> Frame<'static> {
+        Frame::parse(&mut ForwardByteParser::new(COMPRESSED)).unwrap()
+    }
+
+    #[test]
+    fn parse_dictionary_ok() {
+        let dictionary = Dictionary::parse(DICTIONARY).unwrap();
+
+        assert_eq!(0x111cbc5, dictionary.id);
+        assert!(!dictionary.content.is_empty());
+        assert!(dictionary
+            .offsets
+            .iter()
+            .all(|&offset| offset > 0 && offset <= dictionary.content.len()));
+    }
+
+    #[test]
+    fn parse_bad_magic_error() {
+        let mut data = DICTIONARY.to_vec();
+        data[0] = 0x42;
+
+        let error = Dictionary::parse(&data).unwrap_err();
+        assert_eq!(ErrorKind::UnrecognizedMagic, error.kind());
+        assert_eq!(Some(0), error.offset());
+        assert!(matches!(
+            error.downcast_ref::<dictionary::Error>(),
+            Some(dictionary::Error::UnrecognizedMagic(0xEC30A442))
+        ));
+    }
+
+    #[test]
+    fn parse_truncated_dictionary_error() {
+        let error = Dictionary::parse(&DICTIONARY[..20]).unwrap_err();
+        assert_eq!(Some(Section::HuffmanTree), error.section());
+        assert_eq!(Some(8), error.offset());
+    }
+
+    #[test]
+    fn parse_invalid_repeat_offset_error() {
+        // The repeat offsets are followed by the content, truncated to a few bytes
+        let dictionary = Dictionary::parse(DICTIONARY).unwrap();
+        let start = DICTIONARY.len() - dictionary.content.len();
+        let error = Dictionary::parse(&DICTIONARY[..start + 4]).unwrap_err();
+
+        assert_eq!(ErrorKind::Corrupted, error.kind());
+        assert_eq!(Some(start as u64 - 12), error.offset());
+        assert!(matches!(
+            error.downcast_ref::<dictionary::Error>(),
+            Some(dictionary::Error::InvalidRepeatOffset(_))
+        ));
+    }
+
+    #[test]
+    fn decode_with_dictionary_ok() {
+        let dictionary = Dictionary::parse(DICTIONARY).unwrap();
+        let Frame::ZStandardFrame(frame) = parse_frame() else {
+            panic!("Unexpected frame type")
+        };
+
+        assert_eq!(Some(0x111cbc5), frame.header().dictionnary_id);
+        assert_eq!(
+            DECOMPRESSED,
+            frame.decode_with_dictionary(&dictionary).unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_without_dictionary_error() {
+        assert!(matches!(
+            parse_frame()
+                .decode()
+                .unwrap_err()
+                .downcast_ref::<frame::Error>(),
+            Some(frame::Error::MissingDictionary(0x111cbc5))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_to_with_dictionary_ok() {
+        let dictionary = Dictionary::parse(DICTIONARY).unwrap();
+        let Frame::ZStandardFrame(frame) = parse_frame() else {
+            panic!("Unexpected frame type")
+        };
+        let mut res = vec![];
+        frame
+            .decode_with_dictionary_to(&dictionary, &mut res)
+            .unwrap();
+        assert_eq!(DECOMPRESSED, res);
+
+        let Frame::ZStandardFrame(frame) = parse_frame() else {
+            panic!("Unexpected frame type")
+        };
+        assert!(matches!(
+            frame
+                .decode_to(&mut vec![])
+                .unwrap_err()
+                .downcast_ref::<frame::Error>(),
+            Some(frame::Error::MissingDictionary(0x111cbc5))
+        ));
+    }
+
+    #[test]
+    fn decode_with_wrong_dictionary_error() {
+        let mut dictionary = Dictionary::parse(DICTIONARY).unwrap();
+        dictionary.id = 42;
+        let Frame::ZStandardFrame(frame) = parse_frame() else {
+            panic!("Unexpected frame type")
+        };
+
+        assert!(matches!(
+            frame
+                .decode_with_dictionary(&dictionary)
+                .unwrap_err()
+                .downcast_ref::<frame::Error>(),
+            Some(frame::Error::DictionaryMismatch {
+                expected: 0x111cbc5,
+                got: 42
+            })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn read_with_dictionary_ok() {
+        let dictionary = Dictionary::parse(DICTIONARY).unwrap();
+        let mut res = vec![];
+        ZstdReader::new(COMPRESSED)
+            .with_dictionary(dictionary)
+            .read_to_end(&mut res)
+            .unwrap();
+
+        assert_eq!(DECOMPRESSED, res);
+    }
+
+    #[test]
+    fn load_raw_content_dictionary() {
+        let dictionary = Dictionary::load(vec![0x10, 0x20, 0x30]).unwrap();
+
+        assert_eq!(0, dictionary.id);
+        assert!(dictionary.entropy.is_none());
+        assert_eq!(vec![0x10, 0x20, 0x30], dictionary.content);
+    }
+
+    #[test]
+    fn load_formatted_dictionary() {
+        let dictionary = Dictionary::load(DICTIONARY.to_vec()).unwrap();
+
+        assert_eq!(0x111cbc5, dictionary.id);
+        assert!(dictionary.entropy.is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_with_prefix_ok() {
+        let mut original = vec![];
+        ZstdReader::new(ROMEO).read_to_end(&mut original).unwrap();
+        let prefix = Dictionary::raw(original);
+
+        let mut res = vec![];
+        ZstdReader::new(PATCH)
+            .with_dictionary(prefix)
+            .read_to_end(&mut res)
+            .unwrap();
+
+        assert_eq!(PATCHED, res);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_with_prefix_missing_error() {
+        let mut res = vec![];
+        assert!(ZstdReader::new(PATCH).read_to_end(&mut res).is_err());
+    }
+}
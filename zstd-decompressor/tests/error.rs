@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod error_tests {
+    #[cfg(feature = "std")]
+    use std::io::Read;
+
+    #[cfg(feature = "std")]
+    use zstd_decompressor::reader::ZstdReader;
+    use zstd_decompressor::{decompressor::Decompressor, Error, ErrorKind, Section};
+
+    const MOBY_DICK: &[u8] = include_bytes!("../../resources/moby-dick.txt.zst");
+
+    fn corrupted(position: usize) -> Vec<u8> {
+        let mut data = MOBY_DICK.to_vec();
+        data[position] ^= 0x5a;
+        data
+    }
+
+    /// Decompress `data` with both the decompressor and the streaming reader, which must
+    /// report the same error
+    fn decompress_error(data: &[u8]) -> Error {
+        let error = Decompressor::new().decompress(data).unwrap_err();
+        #[cfg(feature = "std")]
+        assert_same_reader_error(data, &error);
+
+        error
+    }
+
+    #[cfg(feature = "std")]
+    fn assert_same_reader_error(data: &[u8], error: &Error) {
+        let reader_error = ZstdReader::new(data)
+            .read_to_end(&mut vec![])
+            .unwrap_err()
+            .into_inner()
+            .unwrap()
+            .downcast::<Error>()
+            .unwrap();
+        assert_eq!(error.kind(), reader_error.kind());
+        assert_eq!(error.offset(), reader_error.offset());
+        assert_eq!(error.bit_offset(), reader_error.bit_offset());
+        assert_eq!(error.frame(), reader_error.frame());
+        assert_eq!(error.block(), reader_error.block());
+        assert_eq!(error.section(), reader_error.section());
+    }
+
+    #[test]
+    fn corrupted_literals_located() {
+        let error = decompress_error(&corrupted(100));
+
+        assert_eq!(ErrorKind::Corrupted, error.kind());
+        assert_eq!(Some(0), error.frame());
+        assert_eq!(Some(0), error.block());
+        assert_eq!(Some(Section::HuffmanStreams), error.section());
+        assert!(error.offset().unwrap() <= 100);
+        assert!(error
+            .to_string()
+            .contains("(frame 0, block 0, Huffman streams, at byte"));
+    }
+
+    #[test]
+    fn corrupted_sequences_located() {
+        let position = MOBY_DICK.len() / 2;
+        let error = decompress_error(&corrupted(position));
+
+        assert_eq!(ErrorKind::Corrupted, error.kind());
+        assert_eq!(Some(0), error.frame());
+        assert!(error.block().unwrap() > 0);
+        assert_eq!(Some(Section::SequenceBitstream), error.section());
+        assert!(error.offset().unwrap() <= position as u64);
+        assert!(error.bit_offset().is_some());
+    }
+
+    #[test]
+    fn bad_checksum_located() {
+        let error = decompress_error(&corrupted(1000));
+
+        assert_eq!(ErrorKind::ChecksumMismatch, error.kind());
+        assert_eq!(Some(Section::Checksum), error.section());
+        assert_eq!(Some(MOBY_DICK.len() as u64 - 4), error.offset());
+    }
+
+    #[test]
+    fn unrecognized_magic_located() {
+        let data = [MOBY_DICK, &[0x10, 0x20, 0x30, 0x40]].concat();
+        let error = decompress_error(&data);
+
+        assert_eq!(ErrorKind::UnrecognizedMagic, error.kind());
+        assert_eq!(Some(1), error.frame());
+        assert_eq!(None, error.block());
+        assert_eq!(Some(MOBY_DICK.len() as u64), error.offset());
+    }
+}
@@ -1,16 +1,17 @@
+#![allow(clippy::unusual_byte_groupings)]
 #[cfg(test)]
 mod frame_test {
     use zstd_decompressor::frame::{self, Frame};
     use zstd_decompressor::parsing::ForwardByteParser;
 
     fn get_valid_skippable_parser() -> ForwardByteParser<'static> {
-        return ForwardByteParser::new(&[
+        ForwardByteParser::new(&[
             // Skippable frame with magic 0x184d2a53, length 3, content 0x10 0x20 0x30
             // and an extra byte at the end.
             0x53, 0x2a, 0x4d, 0x18, 0x03, 0x00, 0x00, 0x00, 0x10, 0x20, 0x30,
             0x40,
             //^--- magic (LE) ----^ ^------ 3 (LE) -------^ ^--- content ---^ ^-- extra
-        ]);
+        ])
     }
 
     fn get_valid_z_standard_frame_parser() -> ForwardByteParser<'static> {
@@ -64,11 +65,33 @@ mod frame_test {
     fn parsing_error_on_unknown_frame() {
         let mut parser = ForwardByteParser::new(&[0x10, 0x20, 0x30, 0x40]);
         assert!(matches!(
-            Frame::parse(&mut parser),
-            Err(frame::Error::UnrecognizedMagic(0x40302010))
+            Frame::parse(&mut parser)
+                .unwrap_err()
+                .downcast_ref::<frame::Error>(),
+            Some(frame::Error::UnrecognizedMagic(0x40302010))
         ));
     }
 
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_to_sink_ok() {
+        let data = include_bytes!("../../resources/romeo-wlog10.txt.zst");
+        let Frame::ZStandardFrame(standard) =
+            Frame::parse(&mut ForwardByteParser::new(data)).unwrap()
+        else {
+            panic!("Unexpected frame type")
+        };
+        let mut res = vec![];
+        standard.decode_to(&mut res).unwrap();
+
+        let Frame::ZStandardFrame(standard) =
+            Frame::parse(&mut ForwardByteParser::new(data)).unwrap()
+        else {
+            panic!("Unexpected frame type")
+        };
+        assert_eq!(standard.decode().unwrap(), res);
+    }
+
     #[test]
     fn decode_skippable_frame_test() {
         let mut parser = get_valid_skippable_parser();
@@ -81,7 +104,7 @@ mod frame_test {
 #[cfg(test)]
 pub mod skippable_frame_tests {
     use zstd_decompressor::{
-        frame::{self, Frame},
+        frame::Frame,
         parsing::{self, ForwardByteParser},
     };
 
@@ -97,11 +120,11 @@ pub mod skippable_frame_tests {
         let res = Frame::parse(&mut parser);
 
         assert!(matches!(
-            res,
-            Err(frame::Error::ParsingError(parsing::Error::NotEnoughBytes {
+            res.unwrap_err().downcast_ref::<parsing::Error>(),
+            Some(parsing::Error::NotEnoughBytes {
                 requested: 3,
                 available: 2
-            }))
+            })
         ));
         assert_eq!(parser.len(), 2); // did not read the truncated data
     }
@@ -117,11 +140,11 @@ pub mod skippable_frame_tests {
         let res = Frame::parse(&mut parser);
 
         assert!(matches!(
-            res,
-            Err(frame::Error::ParsingError(parsing::Error::NotEnoughBytes {
+            res.unwrap_err().downcast_ref::<parsing::Error>(),
+            Some(parsing::Error::NotEnoughBytes {
                 requested: 4,
                 available: 3
-            }))
+            })
         ));
         assert_eq!(parser.len(), 3);
     }
@@ -136,11 +159,11 @@ pub mod skippable_frame_tests {
         let res = Frame::parse(&mut parser);
 
         assert!(matches!(
-            res,
-            Err(frame::Error::ParsingError(parsing::Error::NotEnoughBytes {
+            res.unwrap_err().downcast_ref::<parsing::Error>(),
+            Some(parsing::Error::NotEnoughBytes {
                 requested: 4,
                 available: 3
-            }))
+            })
         ));
         assert_eq!(parser.len(), 3);
     }
@@ -161,7 +184,7 @@ pub mod frame_header_tests {
 
         let h = Header::parse(&mut parser).unwrap();
 
-        assert_eq!(h.content_checksum_flag, false, "Content checksum is not OK");
+        assert!(!h.content_checksum_flag, "Content checksum is not OK");
         assert_eq!(h.window_size, 0xcccc + 256, "Window size is not OK");
         assert_eq!(h.content_size, Some(0xcccc + 256), "Content size is not OK");
         assert_eq!(h.dictionnary_id, None, "Dictionnary ID is not OK");
@@ -180,7 +203,7 @@ pub mod frame_header_tests {
 
         let h = Header::parse(&mut parser).unwrap();
 
-        assert_eq!(h.content_checksum_flag, false, "Content checksum is not OK");
+        assert!(!h.content_checksum_flag, "Content checksum is not OK");
         assert_eq!(h.window_size, 1024, "Window size is not OK");
         assert_eq!(h.content_size, Some(0xddcc + 256), "Content size is not OK");
         assert_eq!(h.dictionnary_id, None, "Dictionnary ID is not OK");
@@ -213,7 +236,7 @@ pub mod frame_header_tests {
 
         let h = Header::parse(&mut parser).unwrap();
 
-        assert_eq!(h.content_checksum_flag, false, "Content checksum is not OK");
+        assert!(!h.content_checksum_flag, "Content checksum is not OK");
         assert_eq!(h.window_size, 1024, "Window size is not OK");
         assert_eq!(h.content_size, Some(0xddcc + 256), "Content size is not OK");
         assert_eq!(h.dictionnary_id, Some(0xabef), "Dictionnary ID is not OK");
@@ -242,7 +265,7 @@ pub mod frame_header_tests {
 
         let h = Header::parse(&mut parser).unwrap();
 
-        assert_eq!(h.content_checksum_flag, false, "Content checksum is not OK");
+        assert!(!h.content_checksum_flag, "Content checksum is not OK");
         assert_eq!(h.window_size, 1024, "Window size is not OK");
         assert_eq!(
             h.content_size,
@@ -260,7 +283,7 @@ pub mod frame_header_tests {
 #[cfg(test)]
 pub mod z_standard_frame_tests {
     use zstd_decompressor::parsing::ForwardByteParser;
-    use zstd_decompressor::{frame, parsing};
+    use zstd_decompressor::{decoding_context, frame, parsing, ErrorKind, Section};
 
     // only need to test errors cases as this only builds upon other tested functions
     #[test]
@@ -329,17 +352,20 @@ pub mod z_standard_frame_tests {
             0x42, //< // no checksum at the end, should throw error
         ]);
 
-        let res = frame::ZStandard::parse(&mut parser);
+        let error = frame::ZStandard::parse(&mut parser).unwrap_err();
 
         assert!(matches!(
-            res,
-            Err(frame::Error::MissingChecksum(
+            error.downcast_ref::<frame::Error>(),
+            Some(frame::Error::MissingChecksum(
                 parsing::Error::NotEnoughBytes {
                     requested: 4,
                     available: 1
                 }
             ))
-        ))
+        ));
+        assert_eq!(ErrorKind::UnexpectedEnd, error.kind());
+        assert_eq!(Some(Section::Checksum), error.section());
+        assert_eq!(Some(10), error.offset());
     }
 
     #[test]
@@ -363,13 +389,83 @@ pub mod z_standard_frame_tests {
 
         let _got = (1u64 << 41) + 7 * (1u64 << 38);
         assert!(matches!(
-            res,
-            Err(frame::Error::WindowSizeTooBig {
+            res.unwrap_err().downcast_ref::<decoding_context::Error>(),
+            Some(decoding_context::Error::WindowSizeTooBig {
                 max: frame::MAX_WIN_SIZE,
                 got: _got
             })
         ))
     }
+
+    /// Frame with a 4 bytes content size, containing one raw block of 4 bytes
+    fn raw_block_frame(content_size: u8) -> Vec<u8> {
+        vec![
+            0b10_0_0_0_0_00, // FCS is 4 bytes, w/o checksum, not single segment
+            0x0,             // 1KiB window
+            content_size,
+            0x0,
+            0x0,
+            0x0,
+            0x21, // block header: raw block of 4 bytes and last one
+            0x0,
+            0x0, // <
+            0x10,
+            0x20,
+            0x30,
+            0x40,
+        ]
+    }
+
+    #[test]
+    fn decode_matching_content_size_ok() {
+        let data = raw_block_frame(4);
+        let res = frame::ZStandard::parse(&mut ForwardByteParser::new(&data)).unwrap();
+
+        assert_eq!(vec![0x10, 0x20, 0x30, 0x40], res.decode().unwrap());
+    }
+
+    #[test]
+    fn decode_preallocates_content_size() {
+        let data = include_bytes!("../../resources/moby-dick.txt.zst");
+        let frame::Frame::ZStandardFrame(res) =
+            frame::Frame::parse(&mut ForwardByteParser::new(data)).unwrap()
+        else {
+            panic!("moby-dick.txt.zst is a ZStandard frame");
+        };
+        let content_size = res.header().content_size.unwrap();
+
+        let decoded = res.decode().unwrap();
+        assert_eq!(content_size, decoded.len() as u64);
+        assert_eq!(decoded.len(), decoded.capacity());
+    }
+
+    #[test]
+    fn decode_content_size_too_big_error() {
+        let data = raw_block_frame(3);
+        let res = frame::ZStandard::parse(&mut ForwardByteParser::new(&data)).unwrap();
+
+        assert!(matches!(
+            res.decode().unwrap_err().downcast_ref::<frame::Error>(),
+            Some(frame::Error::ContentSizeTooBig(3))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_content_size_too_small_error() {
+        let data = raw_block_frame(5);
+        let res = frame::ZStandard::parse(&mut ForwardByteParser::new(&data)).unwrap();
+
+        assert!(matches!(
+            res.decode_to(&mut vec![])
+                .unwrap_err()
+                .downcast_ref::<frame::Error>(),
+            Some(frame::Error::ContentSizeTooSmall {
+                expected: 5,
+                got: 4
+            })
+        ));
+    }
 }
 
 #[cfg(test)]
@@ -377,10 +473,10 @@ pub mod frame_iterator_tests {
     use zstd_decompressor::parsing::ForwardByteParser;
 
     fn get_valid_skippable_parser() -> ForwardByteParser<'static> {
-        return ForwardByteParser::new(&[
+        ForwardByteParser::new(&[
             0x53, 0x2a, 0x4d, 0x18, 0x03, 0x00, 0x00, 0x00, 0x10, 0x20, 0x30, 0x51, 0x2a, 0x4d,
             0x18, 0x04, 0x00, 0x00, 0x00, 0x10, 0x20, 0x30, 0x40,
-        ]);
+        ])
     }
 
     #[test]
@@ -397,3 +493,81 @@ pub mod frame_iterator_tests {
         );
     }
 }
+
+#[cfg(test)]
+pub mod checksum_tests {
+    #[cfg(feature = "std")]
+    use std::io::Read;
+
+    #[cfg(feature = "std")]
+    use zstd_decompressor::reader::ZstdReader;
+    use zstd_decompressor::{
+        frame::{self, ChecksumPolicy, Frame, ZStandard},
+        parsing::ForwardByteParser,
+    };
+
+    const ROMEO: &[u8] = include_bytes!("../../resources/romeo.txt.zst");
+
+    /// Same frame as `ROMEO` with a modified checksum
+    fn corrupted() -> Vec<u8> {
+        let mut data = ROMEO.to_vec();
+        *data.last_mut().unwrap() ^= 0xff;
+        data
+    }
+
+    fn parse(data: &[u8]) -> ZStandard<'_> {
+        let Frame::ZStandardFrame(frame) = Frame::parse(&mut ForwardByteParser::new(data)).unwrap()
+        else {
+            panic!("Unexpected frame type")
+        };
+        frame
+    }
+
+    #[test]
+    fn valid_checksum_ok() {
+        assert!(parse(ROMEO).checksum().is_some());
+        assert!(parse(ROMEO).decode().is_ok());
+    }
+
+    #[test]
+    fn bad_checksum_error() {
+        let data = corrupted();
+        assert!(matches!(
+            parse(&data)
+                .decode()
+                .unwrap_err()
+                .downcast_ref::<frame::Error>(),
+            Some(frame::Error::BadCheksum)
+        ));
+    }
+
+    #[test]
+    fn bad_checksum_warn_or_ignore_ok() {
+        let data = corrupted();
+        let expected = parse(ROMEO).decode().unwrap();
+
+        for policy in [ChecksumPolicy::Warn, ChecksumPolicy::Ignore] {
+            assert_eq!(
+                expected,
+                parse(&data).with_checksum_policy(policy).decode().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn bad_checksum_reader_error() {
+        let data = corrupted();
+        let mut res = vec![];
+        assert!(ZstdReader::new(data.as_slice())
+            .read_to_end(&mut res)
+            .is_err());
+
+        res.clear();
+        ZstdReader::new(data.as_slice())
+            .with_checksum_policy(ChecksumPolicy::Ignore)
+            .read_to_end(&mut res)
+            .unwrap();
+        assert_eq!(parse(ROMEO).decode().unwrap(), res);
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod literals_tests {
+    use zstd_decompressor::{
+        decoders::huffman::HuffmanDecoder,
+        decoding_context::DecodingContext,
+        frame,
+        literals::{self, LiteralsSection},
+        Error, Section,
+    };
+
+    /// Stream decoding to "BABCBB" with `huffman_decoder`
+    const STREAM: [u8; 2] = [0x97, 0x01];
+    const STREAMS: [u8; 8] = [0x97, 0x01, 0x97, 0x01, 0x97, 0x01, 0x97, 0x01];
+
+    fn huffman_decoder() -> HuffmanDecoder {
+        let weights: Vec<_> = std::iter::repeat_n(0, 65).chain([1, 2]).collect();
+        HuffmanDecoder::from_weights(weights).unwrap()
+    }
+
+    fn decode(
+        regenerated_size: usize,
+        n_streams: u8,
+        jump_table: [usize; 4],
+        data: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let mut context = DecodingContext::new(frame::MAX_WIN_SIZE).unwrap();
+        LiteralsSection::CompressedLiteralsBlock {
+            huffman_decoder: Some(huffman_decoder()),
+            regenerated_size,
+            n_streams,
+            jump_table,
+            data,
+        }
+        .decode(&mut context)
+    }
+
+    #[test]
+    fn single_stream_ok() {
+        assert_eq!(
+            b"BABCBB".to_vec(),
+            decode(6, 1, [2, 0, 0, 0], &STREAM).unwrap()
+        );
+    }
+
+    #[test]
+    fn single_stream_regenerated_size_error() {
+        assert!(matches!(
+            decode(5, 1, [2, 0, 0, 0], &STREAM)
+                .unwrap_err()
+                .downcast_ref::<literals::Error>(),
+            Some(literals::Error::RegeneratedSizeMismatch {
+                expected: 5,
+                got: 6
+            })
+        ));
+    }
+
+    #[test]
+    fn four_streams_ok() {
+        assert_eq!(
+            b"BABCBB".repeat(4),
+            decode(24, 4, [2, 2, 2, 2], &STREAMS).unwrap()
+        );
+    }
+
+    #[test]
+    fn four_streams_size_error() {
+        // The last stream should only hold 5 literals
+        assert!(matches!(
+            decode(23, 4, [2, 2, 2, 2], &STREAMS)
+                .unwrap_err()
+                .downcast_ref::<literals::Error>(),
+            Some(literals::Error::StreamSizeMismatch {
+                stream: 3,
+                expected: 5,
+                got: 6
+            })
+        ));
+        // The first streams should hold 7 literals each
+        assert!(matches!(
+            decode(25, 4, [2, 2, 2, 2], &STREAMS)
+                .unwrap_err()
+                .downcast_ref::<literals::Error>(),
+            Some(literals::Error::StreamSizeMismatch {
+                stream: 0,
+                expected: 7,
+                got: 6
+            })
+        ));
+        assert!(matches!(
+            decode(1, 4, [2, 2, 2, 2], &STREAMS)
+                .unwrap_err()
+                .downcast_ref::<literals::Error>(),
+            Some(literals::Error::RegeneratedSizeTooSmall(1))
+        ));
+    }
+
+    #[test]
+    fn stream_error_location() {
+        let error = decode(23, 4, [2, 2, 2, 2], &STREAMS).unwrap_err();
+        assert_eq!(Some(Section::HuffmanStreams), error.section());
+    }
+
+    #[test]
+    fn four_streams_jump_table_error() {
+        assert!(matches!(
+            decode(24, 4, [2, 2, 2, 3], &STREAMS)
+                .unwrap_err()
+                .downcast_ref::<literals::Error>(),
+            Some(literals::Error::CorruptedStreamsSizeTooBig)
+        ));
+    }
+}
@@ -1 +1,2 @@
+#![allow(clippy::unusual_byte_groupings)]
 mod decoders;
@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod no_panic_tests {
+    #[cfg(feature = "std")]
+    use std::io::Read;
+    use std::panic;
+
+    #[cfg(feature = "std")]
+    use zstd_decompressor::reader::ZstdReader;
+    use zstd_decompressor::{dictionary::Dictionary, frame::Frame, parsing::ForwardByteParser};
+
+    const SAMPLES: [&[u8]; 8] = [
+        include_bytes!("../../resources/romeo3.txt.zst"),
+        include_bytes!("../../resources/romeo.txt.zst"),
+        include_bytes!("../../resources/romeo-wlog10.txt.zst"),
+        include_bytes!("../../resources/moby-dick-sample.txt.zst"),
+        include_bytes!("../../resources/skippables.zst"),
+        include_bytes!("../../resources/welcome.zst"),
+        include_bytes!("../../resources/romeo-19-wlog10.txt.zst"),
+        include_bytes!("../../resources/romeo-patched.txt.zst"),
+    ];
+    const DICTIONARY: &[u8] = include_bytes!("../../resources/moby-dick.dict");
+
+    /// Number of mutated inputs tried by each test, can be raised with the
+    /// `ZSTD_FUZZ_ITERATIONS` environment variable for longer runs
+    fn iterations() -> usize {
+        std::env::var("ZSTD_FUZZ_ITERATIONS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2000)
+    }
+
+    /// Small xorshift generator, so that failures are reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n.max(1) as u64) as usize
+        }
+    }
+
+    /// Apply a few random mutations (bit flips, byte changes, truncation, insertion and
+    /// removal of bytes) to `data`
+    fn mutate(rng: &mut Rng, data: &[u8]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        for _ in 0..1 + rng.below(4) {
+            let pos = rng.below(data.len());
+            match rng.below(6) {
+                0 | 1 if !data.is_empty() => data[pos] ^= 1 << rng.below(8),
+                2 if !data.is_empty() => data[pos] = rng.next() as u8,
+                3 => data.truncate(pos),
+                4 => data.insert(pos, rng.next() as u8),
+                _ if !data.is_empty() => {
+                    data.remove(pos);
+                }
+                _ => (),
+            }
+        }
+        data
+    }
+
+    /// Decode `data` in memory and, with `std`, through the streaming reader, ignoring
+    /// errors
+    fn decode_all(data: &[u8], dictionary: Option<&Dictionary>) {
+        for frame in ForwardByteParser::new(data).iter() {
+            match frame {
+                Ok(Frame::ZStandardFrame(frame)) => {
+                    let _ = match dictionary {
+                        Some(dictionary) => frame.decode_with_dictionary(dictionary),
+                        None => frame.decode(),
+                    };
+                }
+                Ok(frame) => {
+                    let _ = frame.decode();
+                }
+                Err(_) => break,
+            }
+        }
+
+        #[cfg(feature = "std")]
+        read_all(data, dictionary);
+    }
+
+    #[cfg(feature = "std")]
+    fn read_all(data: &[u8], dictionary: Option<&Dictionary>) {
+        let mut reader = ZstdReader::new(data).include_skippable(true);
+        if let Some(dictionary) = dictionary {
+            reader = reader.with_dictionary(dictionary.clone());
+        }
+        let _ = reader.read_to_end(&mut vec![]);
+    }
+
+    fn assert_no_panic(data: &[u8], dictionary: Option<&Dictionary>) {
+        if panic::catch_unwind(|| decode_all(data, dictionary)).is_err() {
+            panic!("Decoding panicked on input {data:02x?}");
+        }
+    }
+
+    #[test]
+    fn mutated_samples_never_panic() {
+        let mut rng = Rng(0x5EED_2023);
+        for _ in 0..iterations() {
+            let sample = SAMPLES[rng.below(SAMPLES.len())];
+            assert_no_panic(&mutate(&mut rng, sample), None);
+        }
+    }
+
+    #[test]
+    fn mutated_samples_with_dictionary_never_panic() {
+        let dictionary = Dictionary::parse(DICTIONARY).unwrap();
+        let mut rng = Rng(0xD1C7_2023);
+        for _ in 0..iterations() {
+            assert_no_panic(&mutate(&mut rng, SAMPLES[3]), Some(&dictionary));
+        }
+    }
+
+    #[test]
+    fn random_frames_never_panic() {
+        let mut rng = Rng(0xF4A3_2023);
+        for _ in 0..iterations() {
+            let mut data = 0xFD2FB528u32.to_le_bytes().to_vec();
+            data.extend((0..rng.below(64)).map(|_| rng.next() as u8));
+            assert_no_panic(&data, None);
+        }
+    }
+
+    #[test]
+    fn mutated_dictionaries_never_panic() {
+        let mut rng = Rng(0xD1C7_1234);
+        for _ in 0..iterations() {
+            let data = mutate(&mut rng, DICTIONARY);
+            if panic::catch_unwind(|| Dictionary::parse(&data)).is_err() {
+                panic!("Dictionary parsing panicked on input {data:02x?}");
+            }
+        }
+    }
+}
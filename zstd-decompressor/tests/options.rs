@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod decoder_options_tests {
+    #[cfg(feature = "std")]
+    use std::io::{self, Read};
+
+    #[cfg(not(feature = "std"))]
+    use zstd_decompressor::decompressor::Decompressor;
+    #[cfg(feature = "std")]
+    use zstd_decompressor::reader::ZstdReader;
+    use zstd_decompressor::{
+        frame::Frame, options::DecoderOptions, parsing::ForwardByteParser, Error, ErrorKind,
+    };
+
+    const ROMEO: &[u8] = include_bytes!("../../resources/romeo3.txt.zst");
+    /// Same content as `ROMEO` compressed with `zstd --long=27`, using a 128MiB window
+    const ROMEO_LONG: &[u8] = include_bytes!("../../resources/romeo-long27.txt.zst");
+    const MOBY_DICK: &[u8] = include_bytes!("../../resources/moby-dick.txt.zst");
+    const SKIPPABLES: &[u8] = include_bytes!("../../resources/skippables.zst");
+
+    /// Decode `data` with the streaming reader, whose errors must be `InvalidData`
+    #[cfg(feature = "std")]
+    fn read_with(data: &[u8], options: DecoderOptions) -> Result<Vec<u8>, Error> {
+        let mut res = vec![];
+        ZstdReader::new(data)
+            .with_options(options)
+            .read_to_end(&mut res)
+            .map_err(|e| {
+                assert_eq!(io::ErrorKind::InvalidData, e.kind());
+                *e.into_inner().unwrap().downcast::<Error>().unwrap()
+            })?;
+        Ok(res)
+    }
+
+    /// Decode `data` with the decompressor, the reader needs `std`
+    #[cfg(not(feature = "std"))]
+    fn read_with(data: &[u8], options: DecoderOptions) -> Result<Vec<u8>, Error> {
+        Decompressor::new().with_options(options).decompress(data)
+    }
+
+    #[test]
+    fn large_window_rejected_by_default() {
+        let error = Frame::parse(&mut ForwardByteParser::new(ROMEO_LONG)).unwrap_err();
+        assert_eq!(ErrorKind::WindowTooBig, error.kind());
+        assert!(read_with(ROMEO_LONG, DecoderOptions::default()).is_err());
+    }
+
+    #[test]
+    fn large_window_accepted_with_larger_limit() {
+        let options = DecoderOptions::new().max_window_size(128 << 20);
+        let expected = read_with(ROMEO, DecoderOptions::default()).unwrap();
+
+        assert_eq!(expected, read_with(ROMEO_LONG, options).unwrap());
+
+        let frame = ForwardByteParser::new(ROMEO_LONG)
+            .iter_with_options(options)
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(expected, frame.decode().unwrap());
+    }
+
+    #[test]
+    fn max_output_error() {
+        let options = DecoderOptions::new().max_output(1000);
+
+        let err = read_with(MOBY_DICK, options).unwrap_err();
+        assert_eq!(ErrorKind::OutputTooBig, err.kind());
+
+        let frame =
+            Frame::parse_with_options(&mut ForwardByteParser::new(MOBY_DICK), &options).unwrap();
+        assert_eq!(ErrorKind::OutputTooBig, frame.decode().unwrap_err().kind());
+    }
+
+    #[test]
+    fn max_output_ok() {
+        let expected = read_with(ROMEO, DecoderOptions::default()).unwrap();
+        let options = DecoderOptions::new().max_output(expected.len() as u64);
+
+        assert_eq!(expected, read_with(ROMEO, options).unwrap());
+    }
+
+    #[test]
+    fn max_blocks_error() {
+        let options = DecoderOptions::new().max_blocks(1);
+
+        let error = Frame::parse_with_options(&mut ForwardByteParser::new(MOBY_DICK), &options)
+            .unwrap_err();
+        assert_eq!(ErrorKind::TooManyBlocks, error.kind());
+        assert_eq!(Some(1), error.block());
+
+        // The reader reports the same error, at the same offset
+        let reader_error = read_with(MOBY_DICK, options).unwrap_err();
+        assert_eq!(ErrorKind::TooManyBlocks, reader_error.kind());
+        assert_eq!(Some(1), reader_error.block());
+        assert_eq!(error.offset(), reader_error.offset());
+    }
+
+    #[test]
+    fn max_frames_error() {
+        let options = DecoderOptions::new().max_frames(1).include_skippable(true);
+
+        let mut frames = ForwardByteParser::new(SKIPPABLES).iter_with_options(options);
+        assert!(frames.next().unwrap().is_ok());
+        let error = frames.next().unwrap().unwrap_err();
+        assert_eq!(ErrorKind::TooManyFrames, error.kind());
+        assert_eq!(Some(1), error.frame());
+        assert!(read_with(SKIPPABLES, options).is_err());
+    }
+
+    #[test]
+    fn skippable_frames_ignored_by_iterator() {
+        let frames = ForwardByteParser::new(SKIPPABLES).iter_with_options(DecoderOptions::new());
+        assert_eq!(0, frames.count());
+    }
+}
@@ -0,0 +1,92 @@
+#[cfg(all(test, feature = "std"))]
+mod parallel_tests {
+    use zstd_decompressor::{
+        decompressor::Decompressor, dictionary::Dictionary, options::DecoderOptions,
+        parallel::ParallelDecompressor, ErrorKind,
+    };
+
+    const ROMEO: &[u8] = include_bytes!("../../resources/romeo3.txt.zst");
+    const MOBY_DICK: &[u8] = include_bytes!("../../resources/moby-dick.txt.zst");
+    const SKIPPABLES: &[u8] = include_bytes!("../../resources/skippables.zst");
+    const DICTIONARY: &[u8] = include_bytes!("../../resources/moby-dick.dict");
+    const SAMPLE: &[u8] = include_bytes!("../../resources/moby-dick-sample.txt.zst");
+    const SAMPLE_DECOMPRESSED: &[u8] = include_bytes!("../../resources/moby-dick-sample.txt");
+
+    /// Many frames of different sizes, with skippable frames in between
+    fn multi_frame() -> Vec<u8> {
+        [ROMEO, MOBY_DICK, SKIPPABLES, ROMEO, ROMEO, MOBY_DICK, ROMEO]
+            .concat()
+            .repeat(2)
+    }
+
+    fn decompress(decompressor: &ParallelDecompressor, input: &[u8]) -> Vec<u8> {
+        let mut output = vec![];
+        let written = decompressor.decompress_to(input, &mut output).unwrap();
+        assert_eq!(output.len() as u64, written);
+        output
+    }
+
+    #[test]
+    fn decompress_in_order() {
+        let input = multi_frame();
+        let expected = Decompressor::new().decompress(&input).unwrap();
+
+        for threads in [1, 2, 3, 8] {
+            assert_eq!(
+                expected,
+                decompress(&ParallelDecompressor::new(threads), &input)
+            );
+        }
+    }
+
+    #[test]
+    fn decompress_one_thread_per_core() {
+        assert!(ParallelDecompressor::new(0).threads() > 0);
+    }
+
+    #[test]
+    fn decompress_skippable_frames() {
+        let options = DecoderOptions::new().include_skippable(true);
+        let decompressor = ParallelDecompressor::new(2).with_options(options);
+        assert_eq!(
+            vec![0x10, 0x20, 0x30, 0x42],
+            decompress(&decompressor, SKIPPABLES)
+        );
+    }
+
+    #[test]
+    fn decompress_with_dictionary() {
+        let decompressor =
+            ParallelDecompressor::new(2).with_dictionary(Dictionary::parse(DICTIONARY).unwrap());
+        assert_eq!(
+            SAMPLE_DECOMPRESSED.repeat(3),
+            decompress(&decompressor, &SAMPLE.repeat(3))
+        );
+    }
+
+    #[test]
+    fn decompress_corrupted_frame_error() {
+        let mut input = multi_frame();
+        input.extend_from_slice(&MOBY_DICK[..MOBY_DICK.len() / 2]);
+        input.extend_from_slice(ROMEO);
+
+        let mut output = vec![];
+        let error = ParallelDecompressor::new(4)
+            .decompress_to(&input, &mut output)
+            .unwrap_err();
+        // The error is located in the truncated frame
+        assert!(error.offset().unwrap() >= multi_frame().len() as u64);
+    }
+
+    #[test]
+    fn decompress_max_output_error() {
+        let decompressor =
+            ParallelDecompressor::new(4).with_options(DecoderOptions::new().max_output(100));
+        let mut output = vec![];
+        let error = decompressor
+            .decompress_to(&multi_frame(), &mut output)
+            .unwrap_err();
+        assert_eq!(ErrorKind::OutputTooBig, error.kind());
+        assert!(output.is_empty());
+    }
+}
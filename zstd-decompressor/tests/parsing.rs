@@ -24,18 +24,18 @@ mod forward_byte_parser_tests {
         assert!(parser.len() == 3);
 
         let parser = ForwardByteParser::new(&[]);
-        assert!(parser.len() == 0);
+        assert!(parser.is_empty());
     }
 
     #[test]
     fn is_empty() {
         let arr = [0x12, 0x23, 0x34];
         let parser = ForwardByteParser::new(&arr);
-        assert_eq!(false, parser.is_empty());
+        assert!(!parser.is_empty());
         assert_eq!(0x12, arr[0]);
 
         let parser = ForwardByteParser::new(&[]);
-        assert_eq!(true, parser.is_empty());
+        assert!(parser.is_empty());
     }
 
     #[test]
@@ -53,6 +53,16 @@ mod forward_byte_parser_tests {
         ));
     }
 
+    #[test]
+    fn empty_slice_ok() {
+        let mut parser = ForwardByteParser::new(&[0x12]);
+        assert!(parser.slice(0).unwrap().is_empty());
+        assert_eq!(1, parser.len());
+
+        let mut parser = ForwardByteParser::new(&[]);
+        assert!(parser.slice(0).unwrap().is_empty());
+    }
+
     #[test]
     fn le_u32() {
         // Check that it returns the write value when enough bytes are present
@@ -112,7 +122,7 @@ mod forward_bit_parser_tests {
         let mut parser = parsing::ForwardBitParser::new(&data).unwrap();
         let _ = parser.take(8).unwrap();
 
-        assert!(parser.len() == 0);
+        assert!(parser.is_empty());
     }
 
     #[test]
@@ -157,6 +167,32 @@ mod forward_bit_parser_tests {
         assert!(parser.is_empty());
     }
 
+    #[test]
+    fn peek_consume_ok() {
+        let data = [0b0101_1010, 0b1100_0011];
+
+        let mut parser = parsing::ForwardBitParser::new(&data).unwrap();
+
+        assert_eq!(0b1010, parser.peek(4).unwrap());
+        parser.consume(6).unwrap();
+        assert_eq!(0b11_01, parser.peek(4).unwrap());
+        assert_eq!(1, parser.bytes_read());
+        parser.consume(10).unwrap();
+        assert!(parser.is_empty());
+        assert!(parser.consume(1).is_err());
+    }
+
+    #[test]
+    fn take_64_ok() {
+        let data = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xff];
+
+        let mut parser = parsing::ForwardBitParser::new(&data).unwrap();
+        parser.take(4).unwrap();
+
+        assert_eq!(0xfefc_dab8_9674_5230, parser.take(64).unwrap());
+        assert_eq!(4, parser.len());
+    }
+
     #[test]
     fn take_more_than_64_nok() {
         let data = [1; 10];
@@ -205,10 +241,10 @@ mod backward_bit_parser_tests {
     #[test]
     fn huffman_project_example() {
         // 0 repeated 65 times, 1, 2
-        let weights: Vec<_> = std::iter::repeat(0).take(65).chain([1, 2]).collect();
+        let weights: Vec<_> = std::iter::repeat_n(0, 65).chain([1, 2]).collect();
         let decoder = HuffmanDecoder::from_weights(weights).unwrap();
-        let mut binding = [0x97, 0x01];
-        let mut parser = BackwardBitParser::new(&mut binding).unwrap();
+        let binding = [0x97, 0x01];
+        let mut parser = BackwardBitParser::new(&binding).unwrap();
         let mut result = String::new();
         while !parser.is_empty() {
             let decoded = decoder.decode(&mut parser).unwrap();
@@ -259,7 +295,7 @@ mod backward_bit_parser_tests {
 
         let parser = BackwardBitParser::new(&data).unwrap();
 
-        assert!(parser.len() == 0);
+        assert!(parser.is_empty());
     }
 
     #[test]
@@ -316,6 +352,45 @@ mod backward_bit_parser_tests {
         assert!(parser.is_empty());
     }
 
+    #[test]
+    fn peek_padded_ok() {
+        let data = [0b0101_1010, 0b1100_0011, 1];
+
+        let mut parser = BackwardBitParser::new(&data).unwrap();
+
+        assert_eq!(0b110, parser.peek_padded(3).unwrap());
+        parser.take(10).unwrap();
+        assert_eq!(0b011010, parser.peek_padded(6).unwrap());
+        // Missing bits are zeroes
+        assert_eq!(0b0110_1000, parser.peek_padded(8).unwrap());
+        assert_eq!(6, parser.len());
+    }
+
+    #[test]
+    fn peek_consume_ok() {
+        let data = [0b0101_1010, 0b1100_0011, 1];
+
+        let mut parser = BackwardBitParser::new(&data).unwrap();
+
+        assert_eq!(0b1100, parser.peek(4).unwrap());
+        parser.consume(6).unwrap();
+        assert_eq!(0b11_0101, parser.peek(6).unwrap());
+        assert!(parser.peek(11).is_err());
+        parser.consume(10).unwrap();
+        assert!(parser.is_empty());
+    }
+
+    #[test]
+    fn take_64_ok() {
+        let data = [0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01, 0xff, 0x01];
+
+        let mut parser = BackwardBitParser::new(&data).unwrap();
+        parser.take(4).unwrap();
+
+        assert_eq!(0xf012_3456_789a_bcde, parser.take(64).unwrap());
+        assert_eq!(4, parser.len());
+    }
+
     #[test]
     fn take_more_than_64_nok() {
         let data = [1; 10];
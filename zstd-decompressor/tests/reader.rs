@@ -0,0 +1,120 @@
+#[cfg(all(test, feature = "std"))]
+mod zstd_reader_tests {
+    use std::io::{self, BufRead, Read};
+
+    use zstd_decompressor::{frame::Frame, parsing::ForwardByteParser, reader::ZstdReader};
+
+    const ROMEO: &[u8] = include_bytes!("../../resources/romeo3.txt.zst");
+    const MOBY_DICK: &[u8] = include_bytes!("../../resources/moby-dick.txt.zst");
+    /// Same content as `ROMEO` in a single frame with a 1KiB window
+    const ROMEO_SMALL_WINDOW: &[u8] = include_bytes!("../../resources/romeo-wlog10.txt.zst");
+    const SKIPPABLES: &[u8] = include_bytes!("../../resources/skippables.zst");
+    /// Same content as `ROMEO` compressed with `zstd -19`, some blocks have no literals
+    const ROMEO_EMPTY_LITERALS: &[u8] = include_bytes!("../../resources/romeo-19-wlog10.txt.zst");
+
+    /// Reader returning at most one byte per call
+    struct ByteByByte<'a>(&'a [u8]);
+
+    impl Read for ByteByByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((first, rest)), Some(out)) => {
+                    *out = *first;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn decode_in_memory(data: &[u8]) -> Vec<u8> {
+        let mut res = vec![];
+        for frame in ForwardByteParser::new(data).iter() {
+            if let Frame::ZStandardFrame(_) = frame.as_ref().unwrap() {
+                res.append(&mut frame.unwrap().decode().unwrap());
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn read_matches_in_memory_decoding() {
+        for data in [ROMEO, MOBY_DICK] {
+            let mut res = vec![];
+            ZstdReader::new(data).read_to_end(&mut res).unwrap();
+            assert_eq!(decode_in_memory(data), res);
+        }
+    }
+
+    #[test]
+    fn read_with_window_smaller_than_content() {
+        let mut res = vec![];
+        ZstdReader::new(ROMEO_SMALL_WINDOW)
+            .read_to_end(&mut res)
+            .unwrap();
+        assert_eq!(decode_in_memory(ROMEO), res);
+    }
+
+    #[test]
+    fn read_with_empty_literals() {
+        let mut res = vec![];
+        ZstdReader::new(ROMEO_EMPTY_LITERALS)
+            .read_to_end(&mut res)
+            .unwrap();
+        assert_eq!(decode_in_memory(ROMEO), res);
+    }
+
+    #[test]
+    fn read_from_byte_by_byte_reader() {
+        let mut res = vec![];
+        ZstdReader::new(ByteByByte(ROMEO))
+            .read_to_end(&mut res)
+            .unwrap();
+        assert_eq!(decode_in_memory(ROMEO), res);
+    }
+
+    #[test]
+    fn buf_read_lines() {
+        let lines = ZstdReader::new(ROMEO)
+            .lines()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(Some("Romeo and Juliet"), lines.first().map(String::as_str));
+    }
+
+    #[test]
+    fn skippable_frames_ignored_by_default() {
+        let mut res = vec![];
+        ZstdReader::new(SKIPPABLES).read_to_end(&mut res).unwrap();
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn skippable_frames_included() {
+        let mut res = vec![];
+        ZstdReader::new(SKIPPABLES)
+            .include_skippable(true)
+            .read_to_end(&mut res)
+            .unwrap();
+        assert_eq!(vec![0x10, 0x20, 0x30, 0x42], res);
+    }
+
+    #[test]
+    fn truncated_input_error() {
+        let mut res = vec![];
+        let err = ZstdReader::new(&ROMEO[..ROMEO.len() - 10])
+            .read_to_end(&mut res)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+    }
+
+    #[test]
+    fn unknown_magic_error() {
+        let mut res = vec![];
+        let err = ZstdReader::new(&[0x10, 0x20, 0x30, 0x40][..])
+            .read_to_end(&mut res)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    }
+}
@@ -0,0 +1,192 @@
+#[cfg(all(test, feature = "std"))]
+mod stream_decoder_tests {
+    use std::io::Read;
+
+    use zstd_decompressor::{
+        block::MAX_BLOCK_SIZE,
+        decompressor::Decompressor,
+        dictionary::Dictionary,
+        options::DecoderOptions,
+        reader::ZstdReader,
+        stream::{self, Progress, StreamDecoder},
+        ErrorKind,
+    };
+
+    const ROMEO: &[u8] = include_bytes!("../../resources/romeo3.txt.zst");
+    /// Frame which does not announce its content size
+    const ROMEO_WLOG10: &[u8] = include_bytes!("../../resources/romeo-wlog10.txt.zst");
+    const MOBY_DICK: &[u8] = include_bytes!("../../resources/moby-dick.txt.zst");
+    const SKIPPABLES: &[u8] = include_bytes!("../../resources/skippables.zst");
+    const DICTIONARY: &[u8] = include_bytes!("../../resources/moby-dick.dict");
+    const SAMPLE: &[u8] = include_bytes!("../../resources/moby-dick-sample.txt.zst");
+    const SAMPLE_DECOMPRESSED: &[u8] = include_bytes!("../../resources/moby-dick-sample.txt");
+
+    fn input() -> Vec<u8> {
+        [ROMEO, SKIPPABLES, MOBY_DICK, ROMEO_WLOG10].concat()
+    }
+
+    fn read(data: &[u8], options: DecoderOptions) -> Vec<u8> {
+        let mut res = vec![];
+        ZstdReader::new(data)
+            .with_options(options)
+            .read_to_end(&mut res)
+            .unwrap();
+        res
+    }
+
+    /// Feed `data` in chunks of `chunk_size` bytes
+    fn feed(mut decoder: StreamDecoder, data: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut output = vec![];
+        for chunk in data.chunks(chunk_size) {
+            let progress = decoder.feed(chunk, &mut output).unwrap();
+            assert!(progress.written <= output.len() as u64);
+        }
+        decoder.finish().unwrap();
+        output
+    }
+
+    #[test]
+    fn feed_chunks_ok() {
+        let input = input();
+        let expected = read(&input, DecoderOptions::default());
+
+        for chunk_size in [1, 2, 7, 1000, 100_000, input.len()] {
+            assert_eq!(expected, feed(StreamDecoder::new(), &input, chunk_size));
+        }
+    }
+
+    #[test]
+    fn feed_skippable_frames_ok() {
+        let input = input();
+        let options = DecoderOptions::new().include_skippable(true);
+        let expected = read(&input, options);
+
+        for chunk_size in [1, 3, input.len()] {
+            let decoder = StreamDecoder::new().with_options(options);
+            assert_eq!(expected, feed(decoder, &input, chunk_size));
+        }
+    }
+
+    #[test]
+    fn feed_with_dictionary_ok() {
+        let decoder = StreamDecoder::new().with_dictionary(Dictionary::parse(DICTIONARY).unwrap());
+        assert_eq!(SAMPLE_DECOMPRESSED, feed(decoder, SAMPLE, 10));
+    }
+
+    #[test]
+    fn progress() {
+        let mut decoder = StreamDecoder::new();
+        let mut output = vec![];
+        let (start, end) = ROMEO_WLOG10.split_at(ROMEO_WLOG10.len() / 2);
+
+        let progress = decoder.feed(start, &mut output).unwrap();
+        assert!(progress.needs_more_input);
+        assert!(!progress.frame_finished);
+        assert_eq!(output.len() as u64, progress.written);
+
+        let progress = decoder.feed(end, &mut output).unwrap();
+        assert!(!progress.needs_more_input);
+        assert!(progress.frame_finished);
+
+        assert_eq!(Progress::default(), decoder.feed(&[], &mut output).unwrap());
+        decoder.finish().unwrap();
+        assert_eq!(read(ROMEO_WLOG10, DecoderOptions::default()), output);
+    }
+
+    #[test]
+    fn truncated_input_error() {
+        let mut decoder = StreamDecoder::new();
+        let progress = decoder
+            .feed(&MOBY_DICK[..MOBY_DICK.len() - 3], &mut vec![])
+            .unwrap();
+        assert!(progress.needs_more_input);
+
+        let error = decoder.finish().unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEnd, error.kind());
+        assert_eq!(Some(0), error.frame());
+    }
+
+    #[test]
+    fn corrupted_input_error() {
+        let mut data = MOBY_DICK.to_vec();
+        let position = data.len() / 2;
+        data[position] ^= 0x5a;
+        let expected = Decompressor::new().decompress(&data).unwrap_err();
+
+        for chunk_size in [1, 1000, data.len()] {
+            let mut decoder = StreamDecoder::new();
+            let error = data
+                .chunks(chunk_size)
+                .find_map(|chunk| decoder.feed(chunk, &mut vec![]).err())
+                .unwrap();
+            assert_eq!(expected.kind(), error.kind());
+            assert_eq!(expected.offset(), error.offset());
+            assert_eq!(expected.block(), error.block());
+            assert_eq!(expected.section(), error.section());
+        }
+    }
+
+    #[test]
+    fn feed_after_error() {
+        let mut data = MOBY_DICK.to_vec();
+        let position = data.len() / 2;
+        data[position] ^= 0x5a;
+
+        let mut decoder = StreamDecoder::new();
+        let mut chunks = data.chunks(1000);
+        let error = chunks
+            .find_map(|chunk| decoder.feed(chunk, &mut vec![]).err())
+            .unwrap();
+
+        // The rest of the frame is not decoded as new frames
+        let mut output = vec![];
+        let mut failures: Vec<_> = chunks
+            .map(|chunk| decoder.feed(chunk, &mut output).unwrap_err())
+            .collect();
+        failures.push(decoder.feed(ROMEO, &mut output).unwrap_err());
+        failures.push(decoder.finish().unwrap_err());
+
+        for failure in failures {
+            assert_eq!(error.kind(), failure.kind());
+            assert!(matches!(
+                failure.downcast_ref::<stream::Error>(),
+                Some(stream::Error::Failed(kind)) if *kind == error.kind()
+            ));
+        }
+        assert!(output.is_empty());
+    }
+
+    /// Frame of `blocks` RLE blocks of 128KiB, 4 bytes each
+    fn rle_frame(blocks: usize) -> Vec<u8> {
+        // Magic number, frame header descriptor and a 128KiB window
+        let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd, 0x00, 0x38];
+        for index in 0..blocks {
+            let last = (index + 1 == blocks) as u32;
+            let header = (MAX_BLOCK_SIZE as u32) << 3 | 1 << 1 | last;
+            frame.extend_from_slice(&header.to_le_bytes()[..3]);
+            frame.push(b'a');
+        }
+        frame
+    }
+
+    #[test]
+    fn feed_bounded_output() {
+        let input = [&rle_frame(16)[..], SKIPPABLES, ROMEO].concat();
+        let options = DecoderOptions::new().include_skippable(true);
+        let expected = read(&input, options);
+
+        for max_output in [1, 1000, MAX_BLOCK_SIZE as u64] {
+            let mut decoder = StreamDecoder::new().with_options(options);
+            let mut output = vec![];
+            let mut rest = &input[..];
+            while !rest.is_empty() {
+                let progress = decoder.feed_bounded(rest, &mut output, max_output).unwrap();
+                assert!(progress.consumed > 0);
+                assert!(progress.written < max_output + MAX_BLOCK_SIZE as u64);
+                rest = &rest[progress.consumed..];
+            }
+            decoder.finish().unwrap();
+            assert_eq!(expected, output);
+        }
+    }
+}